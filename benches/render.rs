@@ -0,0 +1,83 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use easy_captcha::captcha::gif::GifCaptcha;
+use easy_captcha::captcha::spec::SpecCaptcha;
+use easy_captcha::{AbstractCaptcha, FontSource, NewCaptcha};
+
+fn render<T: AbstractCaptcha>(mut captcha: T) -> Vec<u8> {
+    let mut data = Vec::new();
+    captcha.out(&mut data).unwrap();
+    data
+}
+
+fn builtin_font(c: &mut Criterion) {
+    c.bench_function("SpecCaptcha::out", |b| {
+        b.iter(|| render(SpecCaptcha::new()))
+    });
+    c.bench_function("GifCaptcha::out", |b| b.iter(|| render(GifCaptcha::new())));
+}
+
+/// 不使用缓存的基准：每次渲染都创建新的字体来源，字体会被重新解析
+fn uncached_font(c: &mut Criterion) {
+    let data = include_bytes!("../resources/actionj.ttf");
+
+    c.bench_function("SpecCaptcha::out (uncached font)", |b| {
+        b.iter(|| {
+            let mut captcha = SpecCaptcha::new();
+            captcha.set_font(FontSource::from_bytes(data.to_vec()).unwrap());
+            render(captcha)
+        })
+    });
+    c.bench_function("GifCaptcha::out (uncached font)", |b| {
+        b.iter(|| {
+            let mut captcha = GifCaptcha::new();
+            captcha.set_font(FontSource::from_bytes(data.to_vec()).unwrap());
+            render(captcha)
+        })
+    });
+}
+
+/// 已解析的字体按线程缓存，新线程的第一次渲染需要重新解析字体
+fn new_thread(c: &mut Criterion) {
+    c.bench_function("SpecCaptcha::out (new thread)", |b| {
+        b.iter(|| {
+            std::thread::spawn(|| render(SpecCaptcha::new()))
+                .join()
+                .unwrap()
+        })
+    });
+    c.bench_function("spawn thread only", |b| {
+        b.iter(|| std::thread::spawn(Vec::<u8>::new).join().unwrap())
+    });
+}
+
+fn system_font(c: &mut Criterion) {
+    // 系统中没有该字体时跳过
+    let font = match FontSource::from_system("DejaVu Sans") {
+        Ok(font) => font,
+        Err(_) => return,
+    };
+
+    c.bench_function("SpecCaptcha::out (system font)", |b| {
+        b.iter(|| {
+            let mut captcha = SpecCaptcha::new();
+            captcha.set_font(font.clone());
+            render(captcha)
+        })
+    });
+    c.bench_function("GifCaptcha::out (system font)", |b| {
+        b.iter(|| {
+            let mut captcha = GifCaptcha::new();
+            captcha.set_font(font.clone());
+            render(captcha)
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    builtin_font,
+    uncached_font,
+    new_thread,
+    system_font
+);
+criterion_main!(benches);
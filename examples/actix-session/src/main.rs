@@ -0,0 +1,56 @@
+use actix_session::storage::CookieSessionStore;
+use actix_session::{Session, SessionMiddleware};
+use actix_web::cookie::Key;
+use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use easy_captcha::captcha::gif::GifCaptcha;
+use easy_captcha::extension::actix_session::{
+    CaptchaActixSessionExt, CaptchaActixSessionStaticExt,
+};
+use easy_captcha::extension::CaptchaUtil;
+use easy_captcha::NewCaptcha;
+use std::collections::HashMap;
+
+/// 接口：获取验证码
+async fn get_captcha(session: Session) -> Result<HttpResponse, easy_captcha::Error> {
+    let mut captcha: CaptchaUtil<GifCaptcha> = CaptchaUtil::with_size_and_len(127, 48, 4);
+    captcha.out(&session)
+}
+
+/// 接口：验证验证码
+async fn verify_captcha(
+    session: Session,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    // 从请求中获取验证码
+    if let Some(code) = query.get("code") {
+        // 调用CaptchaUtil的静态方法验证验证码是否正确，答案会被取出并记录，重新提交的旧Cookie无法再次通过验证
+        if CaptchaUtil::ver(code, &session).await {
+            "Your code is valid, thank you."
+        } else {
+            "Your code is not valid, I'm sorry."
+        }
+    } else {
+        "You didn't provide the code."
+    }
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // 初始化actix-session，生产环境中请使用固定的密钥
+    let key = Key::generate();
+
+    // 启动程序
+    HttpServer::new(move || {
+        App::new()
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), key.clone())
+                    .cookie_secure(false)
+                    .build(),
+            )
+            .route("/captcha", web::get().to(get_captcha))
+            .route("/verify", web::get().to(verify_captcha))
+    })
+    .bind(("127.0.0.1", 8010))?
+    .run()
+    .await
+}
@@ -0,0 +1,342 @@
+//! 验证码的背景 Background of the Captcha image
+
+use crate::base::error::Error;
+use crate::base::randoms::Randoms;
+use raqote::{
+    Color, DrawOptions, DrawTarget, Gradient, GradientStop, PathBuilder, Point, SolidSource,
+    Source, Spread, StrokeStyle,
+};
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+/// 纹理线条的最小间距（像素）
+const MIN_SPACING: f32 = 6.;
+
+/// 纹理线条的最大间距（像素）
+const MAX_SPACING: f32 = 12.;
+
+/// 验证码的背景 Background of the Captcha image
+///
+/// 默认为白色。可以使用纯色、线性或径向渐变、随机的网格或斜线纹理、自定义的PNG图片，或者完全透明的背景。
+/// 渐变的方向、纹理的间距和图片的裁剪位置都是随机的；GIF验证码的每一帧使用相同的背景。
+///
+/// White by default. The background can be a solid color, a linear or radial gradient, a random grid or hatching
+/// texture, a custom PNG image, or fully transparent. The direction of the gradient, the spacing of the texture and
+/// the crop of the image are random; every frame of a GIF Captcha shares the same background.
+///
+/// ```
+/// use easy_captcha::captcha::spec::SpecCaptcha;
+/// use easy_captcha::{AbstractCaptcha, Background, NewCaptcha};
+///
+/// let background = Background::linear_gradient((24, 26, 33), (48, 52, 64));
+/// let mut captcha = SpecCaptcha::new().with_background(background);
+/// captcha.out(Vec::new()).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background(Kind);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Solid(Color),
+    LinearGradient(Color, Color),
+    RadialGradient(Color, Color),
+    Grid(Color, Color),
+    Hatching(Color, Color),
+    Image(Arc<BackgroundImage>),
+    Transparent,
+}
+
+/// 解码后的背景图片，像素为预乘透明度的ARGB
+#[derive(Debug, PartialEq)]
+struct BackgroundImage {
+    width: usize,
+    height: usize,
+    data: Vec<u32>,
+}
+
+fn opaque((r, g, b): (u8, u8, u8)) -> Color {
+    Color::new(255, r, g, b)
+}
+
+impl Background {
+    /// 纯色背景
+    ///
+    /// A solid color.
+    pub fn solid(color: (u8, u8, u8)) -> Self {
+        Self(Kind::Solid(opaque(color)))
+    }
+
+    /// 方向随机的线性渐变
+    ///
+    /// A linear gradient in a random direction.
+    pub fn linear_gradient(from: (u8, u8, u8), to: (u8, u8, u8)) -> Self {
+        Self(Kind::LinearGradient(opaque(from), opaque(to)))
+    }
+
+    /// 中心随机的径向渐变，从中心的`inner`过渡到边缘的`outer`
+    ///
+    /// A radial gradient around a random center, from `inner` at the center to `outer` at the edges.
+    pub fn radial_gradient(inner: (u8, u8, u8), outer: (u8, u8, u8)) -> Self {
+        Self(Kind::RadialGradient(opaque(inner), opaque(outer)))
+    }
+
+    /// 底色上间距和位置随机的网格纹理
+    ///
+    /// A grid with random spacing and offset over the base color.
+    pub fn grid(base: (u8, u8, u8), line: (u8, u8, u8)) -> Self {
+        Self(Kind::Grid(opaque(base), opaque(line)))
+    }
+
+    /// 底色上间距、位置和方向随机的斜线纹理
+    ///
+    /// Diagonal hatching with random spacing, offset and direction over the base color.
+    pub fn hatching(base: (u8, u8, u8), line: (u8, u8, u8)) -> Self {
+        Self(Kind::Hatching(opaque(base), opaque(line)))
+    }
+
+    /// 使用PNG图片作为背景，每次从图片中随机裁剪一块与验证码大小相同的区域；图片小于验证码时会被拉伸
+    ///
+    /// Use a PNG image as the background. A region as large as the Captcha is cropped from it at random each time; the
+    /// image is stretched if it's smaller than the Captcha.
+    pub fn image(png: &[u8]) -> Result<Self, Error> {
+        let invalid = |e: png::DecodingError| {
+            Error::InvalidConfig(format!("The background image is not a valid PNG: {}", e))
+        };
+
+        let mut decoder = png::Decoder::new(png);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(invalid)?;
+
+        let samples = info.color_type.samples();
+        let data = buf[..info.buffer_size()]
+            .chunks_exact(samples)
+            .map(|pixel| {
+                let (r, g, b, a) = match *pixel {
+                    [l] => (l, l, l, 255),
+                    [l, a] => (l, l, l, a),
+                    [r, g, b] => (r, g, b, 255),
+                    [r, g, b, a, ..] => (r, g, b, a),
+                    _ => (0, 0, 0, 0),
+                };
+                let premultiply = |c: u8| (c as u32 * a as u32 + 127) / 255;
+                (a as u32) << 24 | premultiply(r) << 16 | premultiply(g) << 8 | premultiply(b)
+            })
+            .collect();
+
+        Ok(Self(Kind::Image(Arc::new(BackgroundImage {
+            width: info.width as usize,
+            height: info.height as usize,
+            data,
+        }))))
+    }
+
+    /// 完全透明的背景
+    ///
+    /// A fully transparent background.
+    pub fn transparent() -> Self {
+        Self(Kind::Transparent)
+    }
+
+    /// 为一张图像随机生成背景参数；纯色和透明背景不消耗随机数
+    pub(crate) fn sample(&self, randoms: &mut Randoms) -> BackgroundParams {
+        let random = match self.0 {
+            Kind::Solid(_) | Kind::Transparent => [0.; 3],
+            _ => [
+                randoms.float_between(0., 1.),
+                randoms.float_between(0., 1.),
+                randoms.float_between(0., 1.),
+            ],
+        };
+
+        BackgroundParams {
+            background: self.clone(),
+            random,
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::solid((255, 255, 255))
+    }
+}
+
+/// 一张图像实际使用的背景参数
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BackgroundParams {
+    background: Background,
+
+    /// 0到1之间的随机数，按背景的种类决定方向、位置或间距
+    random: [f32; 3],
+}
+
+impl BackgroundParams {
+    /// 填充背景
+    pub(crate) fn apply(&self, dt: &mut DrawTarget) {
+        let (width, height) = (dt.width() as f32, dt.height() as f32);
+        let [r0, r1, r2] = self.random;
+
+        match &self.background.0 {
+            Kind::Solid(color) => fill(dt, &Source::Solid(SolidSource::from(*color))),
+            Kind::LinearGradient(from, to) => {
+                let (sin, cos) = (r0 * TAU).sin_cos();
+                let half = (width * cos.abs() + height * sin.abs()) / 2.;
+                let (cx, cy) = (width / 2., height / 2.);
+                let source = Source::new_linear_gradient(
+                    gradient(*from, *to),
+                    Point::new(cx - cos * half, cy - sin * half),
+                    Point::new(cx + cos * half, cy + sin * half),
+                    Spread::Pad,
+                );
+                fill(dt, &source);
+            }
+            Kind::RadialGradient(inner, outer) => {
+                // 中心位于图像中间的一半区域内，半径覆盖到最远的角
+                let (cx, cy) = (width * (0.25 + r0 / 2.), height * (0.25 + r1 / 2.));
+                let radius = cx.max(width - cx).hypot(cy.max(height - cy));
+                let source = Source::new_radial_gradient(
+                    gradient(*inner, *outer),
+                    Point::new(cx, cy),
+                    radius,
+                    Spread::Pad,
+                );
+                fill(dt, &source);
+            }
+            Kind::Grid(base, line) => {
+                fill(dt, &Source::Solid(SolidSource::from(*base)));
+                let spacing = MIN_SPACING + (MAX_SPACING - MIN_SPACING) * r0;
+                let mut pb = PathBuilder::new();
+                let mut x = spacing * r1;
+                while x < width {
+                    pb.move_to(x, 0.);
+                    pb.line_to(x, height);
+                    x += spacing;
+                }
+                let mut y = spacing * r2;
+                while y < height {
+                    pb.move_to(0., y);
+                    pb.line_to(width, y);
+                    y += spacing;
+                }
+                stroke(dt, pb, *line);
+            }
+            Kind::Hatching(base, line) => {
+                fill(dt, &Source::Solid(SolidSource::from(*base)));
+                let spacing = MIN_SPACING + (MAX_SPACING - MIN_SPACING) * r0;
+                // 斜线向左或向右倾斜45度，起点覆盖到图像左侧之外
+                let slope = if r2 < 0.5 { height } else { -height };
+                let mut pb = PathBuilder::new();
+                let mut x = spacing * r1 - height;
+                while x < width + height {
+                    pb.move_to(x, 0.);
+                    pb.line_to(x + slope, height);
+                    x += spacing;
+                }
+                stroke(dt, pb, *line);
+            }
+            Kind::Image(image) => {
+                // 随机裁剪一块区域，再按最近邻拉伸到图像大小
+                let (w, h) = (dt.width() as usize, dt.height() as usize);
+                let (crop_w, crop_h) = (image.width.min(w), image.height.min(h));
+                let left = ((image.width - crop_w) as f32 * r0) as usize;
+                let top = ((image.height - crop_h) as f32 * r1) as usize;
+
+                let data = dt.get_data_mut();
+                for y in 0..h {
+                    let sy = top + y * crop_h / h;
+                    for x in 0..w {
+                        let sx = left + x * crop_w / w;
+                        data[y * w + x] = image.data[sy * image.width + sx];
+                    }
+                }
+            }
+            Kind::Transparent => dt.clear(SolidSource::from_unpremultiplied_argb(0, 0, 0, 0)),
+        }
+    }
+}
+
+fn gradient(from: Color, to: Color) -> Gradient {
+    Gradient {
+        stops: vec![
+            GradientStop {
+                position: 0.,
+                color: from,
+            },
+            GradientStop {
+                position: 1.,
+                color: to,
+            },
+        ],
+    }
+}
+
+fn fill(dt: &mut DrawTarget, source: &Source) {
+    let (width, height) = (dt.width() as f32, dt.height() as f32);
+    dt.fill_rect(0., 0., width, height, source, &DrawOptions::new());
+}
+
+fn stroke(dt: &mut DrawTarget, pb: PathBuilder, color: Color) {
+    dt.stroke(
+        &pb.finish(),
+        &Source::Solid(SolidSource::from(color)),
+        &StrokeStyle {
+            width: 1.,
+            ..Default::default()
+        },
+        &DrawOptions::new(),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::png::WritePng;
+
+    #[test]
+    fn it_crops_and_stretches_images() {
+        let mut source = DrawTarget::new(4, 4);
+        source.clear(SolidSource::from_unpremultiplied_argb(255, 200, 0, 0));
+        let mut png = Vec::new();
+        source.write_png(&mut png).unwrap();
+
+        let background = Background::image(&png).unwrap();
+        let mut dt = DrawTarget::new(10, 6);
+        background.sample(&mut Randoms::new()).apply(&mut dt);
+        assert!(dt.get_data().iter().all(|pixel| *pixel == 0xffc80000));
+
+        assert!(matches!(
+            Background::image(b"not a png"),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn it_fills_every_style() {
+        let dark = (24, 26, 33);
+        let light = (90, 96, 110);
+        let mut randoms = Randoms::new();
+        for background in [
+            Background::solid(dark),
+            Background::linear_gradient(dark, light),
+            Background::radial_gradient(light, dark),
+            Background::grid(dark, light),
+            Background::hatching(dark, light),
+        ] {
+            let mut dt = DrawTarget::new(130, 48);
+            background.sample(&mut randoms).apply(&mut dt);
+            assert!(dt.get_data().iter().all(|pixel| pixel >> 24 == 0xff));
+        }
+
+        let mut dt = DrawTarget::new(130, 48);
+        Background::solid(dark).sample(&mut randoms).apply(&mut dt);
+        assert!(dt.get_data().iter().all(|pixel| *pixel == 0xff181a21));
+
+        let mut dt = DrawTarget::new(130, 48);
+        Background::transparent()
+            .sample(&mut randoms)
+            .apply(&mut dt);
+        assert!(dt.get_data().iter().all(|pixel| *pixel == 0));
+    }
+}
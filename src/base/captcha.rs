@@ -1,15 +1,29 @@
-use crate::base::randoms::Randoms;
+use crate::base::background::{Background, BackgroundParams};
+use crate::base::charset::Charset;
+use crate::base::error::Error;
+use crate::base::font::{FontError, FontSource};
+use crate::base::layout::{GlyphLayout, GlyphPlacement};
+use crate::base::noise::Noise;
+use crate::base::randoms::{EmptyRange, Randoms};
+use crate::base::transform::{GlyphDistortion, GlyphTransform};
+use crate::base::warp::{Warp, WarpParams};
 
 use crate::utils::color::Color;
 use crate::utils::font;
 use base64::prelude::BASE64_STANDARD;
 use base64::Engine;
+use font_kit::canvas::RasterizationOptions;
 use font_kit::font::Font;
+use font_kit::hinting::HintingOptions;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
 use raqote::{DrawOptions, DrawTarget, PathBuilder, SolidSource, Source, StrokeStyle};
-use std::fmt::Debug;
 use std::io::Write;
-use std::sync::Arc;
+use std::rc::Rc;
+
+/// 变换后的字形与图像边缘之间至少保留的距离（像素），避免抗锯齿的边缘被裁掉
+const CANVAS_MARGIN: f32 = 2.;
 
 /// 验证码抽象类
 pub(crate) struct Captcha {
@@ -19,15 +33,15 @@ pub(crate) struct Captcha {
     /// 常用颜色
     color: Vec<Color>,
 
-    /// 字体名称
-    font_names: [&'static str; 10],
-
-    /// 验证码的字体
-    font_name: String,
+    /// 验证码的字体，有多个字体时每个字符依次轮换使用
+    fonts: Vec<FontSource>,
 
     /// 验证码的字体大小
     font_size: f32,
 
+    /// 用于估算字符宽度的样本字符，为空时使用字符集中最宽的字符
+    pub(crate) sample_char: Option<char>,
+
     /// 验证码随机字符长度
     pub len: usize,
 
@@ -37,14 +51,44 @@ pub(crate) struct Captcha {
     /// 验证码显示高度
     pub height: i32,
 
-    /// 验证码类型
-    char_type: CaptchaType,
+    /// 验证码字符集
+    charset: Charset,
+
+    /// 背景
+    background: Background,
+
+    /// 字符的随机变换
+    glyph_transform: GlyphTransform,
+
+    /// 字符的排列方式
+    glyph_layout: GlyphLayout,
+
+    /// 图像的波浪扭曲
+    warp: Warp,
+
+    /// 干扰元素
+    noise: Noise,
 
     /// 当前验证码
     pub(crate) chars: Option<String>,
 }
 
 /// 验证码文本类型 The character type of the captcha
+///
+/// 每种类型对应的字符集大小及熵如下，验证码的总熵为单个字符的熵乘以验证码长度（默认长度5）：
+///
+/// The size and entropy of the character set of each type are listed below; the total entropy of a Captcha is the
+/// entropy per character multiplied by the length (5 by default):
+///
+/// | 类型/Type         | 字符数/Characters | 每字符熵/Bits per char | 长度5的熵/Bits for len 5 |
+/// |-----------------|----------------|---------------------|-----------------------|
+/// | TypeDefault     | 54             | 5.75                | 28.8                  |
+/// | TypeOnlyNumber  | 8              | 3.00                | 15.0                  |
+/// | TypeOnlyChar    | 46             | 5.52                | 27.6                  |
+/// | TypeOnlyUpper   | 23             | 4.52                | 22.6                  |
+/// | TypeOnlyLower   | 23             | 4.52                | 22.6                  |
+/// | TypeNumAndUpper | 31             | 4.95                | 24.8                  |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CaptchaType {
     /// 字母数字混合
     TypeDefault = 1,
@@ -66,6 +110,7 @@ pub enum CaptchaType {
 }
 
 /// 内置字体 Fonts shipped with the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CaptchaFont {
     /// actionj
     Font1,
@@ -89,64 +134,259 @@ pub enum CaptchaFont {
     Font10,
 }
 
+impl CaptchaFont {
+    /// 内置字体的文件名
+    pub(crate) fn file_name(self) -> &'static str {
+        match self {
+            CaptchaFont::Font1 => "actionj.ttf",
+            CaptchaFont::Font2 => "epilog.ttf",
+            CaptchaFont::Font3 => "fresnel.ttf",
+            CaptchaFont::Font4 => "headache.ttf",
+            CaptchaFont::Font5 => "lexo.ttf",
+            CaptchaFont::Font6 => "prefix.ttf",
+            CaptchaFont::Font7 => "progbot.ttf",
+            CaptchaFont::Font8 => "ransom.ttf",
+            CaptchaFont::Font9 => "robot.ttf",
+            CaptchaFont::Font10 => "scandal.ttf",
+        }
+    }
+}
+
 impl Captcha {
     /// 生成随机验证码
     pub fn alphas(&mut self) -> Vec<char> {
-        let mut cs = vec!['\0'; self.len];
-        for i in 0..self.len {
-            match self.char_type {
-                CaptchaType::TypeDefault => cs[i] = self.randoms.alpha(),
-                CaptchaType::TypeOnlyNumber => {
-                    cs[i] = self.randoms.alpha_under(self.randoms.num_max_index)
-                }
-                CaptchaType::TypeOnlyChar => {
-                    cs[i] = self
-                        .randoms
-                        .alpha_between(self.randoms.char_min_index, self.randoms.char_max_index)
-                }
-                CaptchaType::TypeOnlyUpper => {
-                    cs[i] = self
-                        .randoms
-                        .alpha_between(self.randoms.upper_min_index, self.randoms.upper_max_index)
-                }
-                CaptchaType::TypeOnlyLower => {
-                    cs[i] = self
-                        .randoms
-                        .alpha_between(self.randoms.lower_min_index, self.randoms.lower_max_index)
-                }
-                CaptchaType::TypeNumAndUpper => {
-                    cs[i] = self.randoms.alpha_under(self.randoms.upper_max_index)
-                }
-            }
-        }
+        let cs: Vec<char> = (0..self.len)
+            .map(|_| self.randoms.alpha(&self.charset))
+            .collect();
+
+        self.chars = Some(cs.iter().collect());
+        cs
+    }
+
+    /// 生成随机汉字验证码
+    pub fn alphas_han(&mut self) -> Vec<char> {
+        let cs: Vec<char> = (0..self.len).map(|_| self.randoms.alpha_han()).collect();
 
         self.chars = Some(cs.iter().collect());
         cs
     }
 
+    /// 设置验证码文本类型，已生成的验证码会被清除
+    pub fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.set_charset(char_type.into());
+    }
+
+    /// 设置验证码字符集，已生成的验证码会被清除
+    pub fn set_charset(&mut self, charset: Charset) {
+        self.charset = charset;
+        self.chars = None;
+    }
+
+    /// 设置随机数生成器，已生成的验证码会被清除
+    pub fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.randoms = Randoms::with_rng(rng);
+        self.chars = None;
+    }
+
+    /// 设置背景
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    /// 确定本张图像的背景：渐变的角度、纹理的间距或背景图片的裁剪位置
+    pub fn background_params(&mut self) -> BackgroundParams {
+        self.background.sample(&mut self.randoms)
+    }
+
+    /// 设置字符的随机变换
+    pub fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.glyph_transform = transform;
+    }
+
+    /// 为每个字符随机生成变换；不做变换时不消耗随机数，以保持原有的输出
+    pub fn glyph_distortions(&mut self, len: usize) -> Vec<GlyphDistortion> {
+        if self.glyph_transform.is_none() {
+            return vec![GlyphDistortion::IDENTITY; len];
+        }
+        (0..len)
+            .map(|_| self.glyph_transform.sample(&mut self.randoms))
+            .collect()
+    }
+
+    /// 设置字符的排列方式
+    pub fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.glyph_layout = layout;
+    }
+
+    /// 计算每个字符在图像中的位置和随机变换，字体中没有的字符会返回错误
+    pub(crate) fn glyph_placements(&mut self, str: &[char]) -> Result<Vec<GlyphPlacement>, Error> {
+        let mut glyphs = Vec::with_capacity(str.len());
+        for (i, ch) in str.iter().enumerate() {
+            let font = self.get_font_at(i)?;
+            let glyph = Self::glyph_for_char(&font, *ch)?;
+            glyphs.push((font, glyph));
+        }
+
+        let distortions = self.glyph_distortions(str.len());
+        let (width, height) = (self.width, self.height);
+
+        // 每个字符的起点横坐标、垂直抖动和字形宽度
+        let mut positions = Vec::with_capacity(str.len());
+        if self.glyph_layout.is_cells() {
+            // 格子的边距按样本字符的宽度计算；紧凑排列使用每个字形实际的步进宽度，不需要样本字符
+            let font = self.get_font()?;
+            let sample_glyph = self.sample_glyph(&font)?;
+            let (sample_width, _) = self.glyph_size(&font, sample_glyph)?;
+
+            let f_w = width / str.len() as i32; // 每个字符所占宽度
+            let f_sp = (f_w - sample_width) / 2; // 字符的左右边距
+            for i in 0..str.len() {
+                let x = (i as i32 * f_w + f_sp + 3) as f32;
+                positions.push((x, 0., sample_width as f32));
+            }
+        } else {
+            let mut advances = Vec::with_capacity(str.len());
+            for (font, glyph) in &glyphs {
+                let advance = font
+                    .advance(*glyph)
+                    .map_err(|e| Error::Rendering(e.to_string()))?;
+                let units_per_em = font.metrics().units_per_em as f32;
+                advances.push(advance.x() * self.font_size / units_per_em);
+            }
+
+            let placed = self
+                .glyph_layout
+                .condense(&advances, width as f32, &mut self.randoms);
+            for ((x, dy), advance) in placed.into_iter().zip(advances) {
+                positions.push((x, dy, advance));
+            }
+        }
+
+        let mut placements = Vec::with_capacity(str.len());
+        for (((font, glyph), distortion), (x, dy, glyph_width)) in
+            glyphs.into_iter().zip(distortions).zip(positions)
+        {
+            let (_, glyph_height) = self.glyph_size(&font, glyph)?;
+            if glyph_height > height {
+                return Err(Error::InvalidConfig(format!(
+                    "The characters are taller than the height {} of the Captcha.",
+                    height
+                )));
+            }
+            let f_y = height - ((height - glyph_height) >> 1);
+            let y = (f_y as f32 - 3. + dy).clamp(glyph_height as f32, height as f32);
+
+            let origin = raqote::Point::new(x, y);
+            // 以字符的中心为原点旋转、缩放和倾斜
+            let mut transform =
+                distortion.around(x + glyph_width / 2., y - glyph_height as f32 / 2.);
+            if distortion != GlyphDistortion::IDENTITY {
+                transform = self.keep_on_canvas(&font, glyph, origin, transform)?;
+            }
+
+            placements.push(GlyphPlacement {
+                font,
+                glyph,
+                origin,
+                transform,
+            });
+        }
+
+        Ok(placements)
+    }
+
+    /// 旋转、放大或倾斜后的字形可能超出图像，此时将其平移回图像内并留出少许边距；字形比图像还大时使其居中
+    fn keep_on_canvas(
+        &self,
+        font: &Font,
+        glyph: u32,
+        origin: raqote::Point,
+        transform: raqote::Transform,
+    ) -> Result<raqote::Transform, Error> {
+        let bounds = font
+            .typographic_bounds(glyph)
+            .map_err(|e| Error::Rendering(e.to_string()))?;
+        let scale = self.font_size / font.metrics().units_per_em as f32;
+
+        // 字体坐标的y轴向上，图像坐标的y轴向下
+        let (mut left, mut top) = (f32::MAX, f32::MAX);
+        let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+        for (gx, gy) in [
+            (bounds.min_x(), bounds.min_y()),
+            (bounds.min_x(), bounds.max_y()),
+            (bounds.max_x(), bounds.min_y()),
+            (bounds.max_x(), bounds.max_y()),
+        ] {
+            let corner = transform.transform_point(raqote::Point::new(
+                origin.x + gx * scale,
+                origin.y - gy * scale,
+            ));
+            left = left.min(corner.x);
+            right = right.max(corner.x);
+            top = top.min(corner.y);
+            bottom = bottom.max(corner.y);
+        }
+
+        let shift = |low: f32, high: f32, size: i32| {
+            let (min, max) = (CANVAS_MARGIN, size as f32 - CANVAS_MARGIN);
+            if high - low > max - min {
+                (size as f32 - low - high) / 2.
+            } else if low < min {
+                min - low
+            } else if high > max {
+                max - high
+            } else {
+                0.
+            }
+        };
+        let (dx, dy) = (
+            shift(left, right, self.width),
+            shift(top, bottom, self.height),
+        );
+        Ok(transform.then(&raqote::Transform::translation(dx, dy)))
+    }
+
+    /// 设置图像的波浪扭曲
+    pub fn set_warp(&mut self, warp: Warp) {
+        self.warp = warp;
+    }
+
+    /// 抽取本张图像的振幅和相位，GIF的每一帧共用同一组参数；不做扭曲时返回`None`
+    pub fn warp_params(&mut self) -> Option<WarpParams> {
+        self.warp.sample(&mut self.randoms)
+    }
+
+    /// 设置干扰元素
+    pub fn set_noise(&mut self, noise: Noise) {
+        self.noise = noise;
+    }
+
     /// 给定范围获得随机颜色
-    pub fn color_range(&mut self, fc: u8, bc: u8) -> Color {
-        let r = fc + self.randoms.num((bc - fc) as usize) as u8;
-        let g = fc + self.randoms.num((bc - fc) as usize) as u8;
-        let b = fc + self.randoms.num((bc - fc) as usize) as u8;
-        return (r, g, b).into();
+    pub fn color_range(&mut self, fc: u8, bc: u8) -> Result<Color, EmptyRange> {
+        let r = self.randoms.num_between(fc as i32, bc as i32)? as u8;
+        let g = self.randoms.num_between(fc as i32, bc as i32)? as u8;
+        let b = self.randoms.num_between(fc as i32, bc as i32)? as u8;
+        Ok((r, g, b).into())
     }
 
     /// 获取随机常用颜色
     pub fn color(&mut self) -> Color {
-        self.color[self.randoms.num(self.color.len())].clone()
+        // 常用颜色不会为空
+        self.randoms
+            .pick(&self.color)
+            .cloned()
+            .unwrap_or_else(|| (0, 0, 0).into())
     }
 
     /// 获取当前的验证码
     pub fn text(&mut self) -> String {
         self.check_alpha();
-        self.chars.clone().unwrap()
+        self.chars.clone().unwrap_or_default()
     }
 
     /// 获取当前验证码的字符数组
     pub fn text_char(&mut self) -> Vec<char> {
-        self.check_alpha();
-        self.chars.clone().unwrap().chars().collect()
+        self.text().chars().collect()
     }
 
     /// 检查验证码是否生成，没有则立即生成
@@ -156,16 +396,37 @@ impl Captcha {
         }
     }
 
+    /// 检查验证码的尺寸和字符数量是否有效
+    pub fn check_config(&self, len: usize) -> Result<(), Error> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(Error::InvalidConfig(format!(
+                "The size {}x{} is not positive.",
+                self.width, self.height
+            )));
+        }
+        if len == 0 {
+            return Err(Error::InvalidConfig(
+                "The Captcha contains no characters.".into(),
+            ));
+        }
+        Ok(())
+    }
+
     /// 随机画干扰线
-    pub fn draw_line(&mut self, num: usize, g: &mut DrawTarget, color: Option<Color>) {
+    pub fn draw_line(
+        &mut self,
+        num: usize,
+        g: &mut DrawTarget,
+        color: Option<Color>,
+    ) -> Result<(), EmptyRange> {
         for _ in 0..num {
             let color = color.clone().unwrap_or_else(|| self.color());
             let color: raqote::Color = color.into();
 
-            let x1 = self.randoms.num_between(-10, self.width - 10);
-            let y1 = self.randoms.num_between(5, self.height - 5);
-            let x2 = self.randoms.num_between(10, self.width + 10);
-            let y2 = self.randoms.num_between(2, self.height - 2);
+            let x1 = self.randoms.num_between(-10, self.width - 10)?;
+            let y1 = self.randoms.num_between(5, self.height - 5)?;
+            let x2 = self.randoms.num_between(10, self.width + 10)?;
+            let y2 = self.randoms.num_between(2, self.height - 2)?;
 
             let mut pb = PathBuilder::new();
             pb.move_to(x1 as f32, y1 as f32);
@@ -176,17 +437,24 @@ impl Captcha {
                 &path,
                 &Source::Solid(SolidSource::from(color)),
                 &StrokeStyle {
-                    width: 2.,
+                    width: self.noise.stroke_width.unwrap_or(2.),
                     ..Default::default()
                 },
                 &DrawOptions::new(),
             );
         }
+
+        Ok(())
     }
 
     /// 随机画干扰圆
-    pub fn draw_oval(&mut self, num: usize, g: &mut DrawTarget, color: Option<Color>) {
-        self.draw_oval_with_option(num, g, color, DrawOptions::new());
+    pub fn draw_oval(
+        &mut self,
+        num: usize,
+        g: &mut DrawTarget,
+        color: Option<Color>,
+    ) -> Result<(), EmptyRange> {
+        self.draw_oval_with_option(num, g, color, DrawOptions::new())
     }
 
     /// 随机画干扰圆（包含选项）
@@ -196,14 +464,14 @@ impl Captcha {
         g: &mut DrawTarget,
         color: Option<Color>,
         options: DrawOptions,
-    ) {
+    ) -> Result<(), EmptyRange> {
         for _ in 0..num {
             let color = color.clone().unwrap_or_else(|| self.color());
             let color: raqote::Color = color.into();
 
-            let w = 5 + self.randoms.num(10);
-            let x = self.randoms.num(self.width as usize - 25) + w;
-            let y = self.randoms.num(self.height as usize - 15) + w;
+            let w = 5 + self.randoms.num_between(0, 10)?;
+            let x = self.randoms.num_between(0, self.width - 25)? + w;
+            let y = self.randoms.num_between(0, self.height - 15)? + w;
 
             let mut pb = PathBuilder::new();
             pb.arc(x as f32, y as f32, w as f32, 0., 2. * std::f32::consts::PI);
@@ -213,16 +481,23 @@ impl Captcha {
                 &path,
                 &Source::Solid(SolidSource::from(color)),
                 &StrokeStyle {
-                    width: 2.,
+                    width: self.noise.stroke_width.unwrap_or(2.),
                     ..Default::default()
                 },
                 &options,
             );
         }
+
+        Ok(())
     }
 
     /// 随机画干扰贝塞尔曲线
-    pub fn draw_bessel_line(&mut self, num: usize, g: &mut DrawTarget, color: Option<Color>) {
+    pub fn draw_bessel_line(
+        &mut self,
+        num: usize,
+        g: &mut DrawTarget,
+        color: Option<Color>,
+    ) -> Result<(), EmptyRange> {
         self.draw_bessel_line_with_all_option(
             num,
             g,
@@ -243,33 +518,37 @@ impl Captcha {
         color: Option<Color>,
         stroke_style: StrokeStyle,
         draw_options: DrawOptions,
-    ) {
+    ) -> Result<(), EmptyRange> {
         for _ in 0..num {
             let color = color.clone().unwrap_or_else(|| self.color());
             let color: raqote::Color = color.into();
 
             let x1 = 5;
-            let mut y1 = self.randoms.num_between(5, self.height / 2);
+            let mut y1 = self.randoms.num_between(5, self.height / 2)?;
             let x2 = self.width - 5;
-            let mut y2 = self.randoms.num_between(self.height / 2, self.height - 5);
+            let mut y2 = self.randoms.num_between(self.height / 2, self.height - 5)?;
 
-            let cx = self.randoms.num_between(self.width / 4, self.width / 4 * 3);
-            let cy = self.randoms.num_between(5, self.height - 5);
+            let cx = self
+                .randoms
+                .num_between(self.width / 4, self.width / 4 * 3)?;
+            let cy = self.randoms.num_between(5, self.height - 5)?;
 
-            if self.randoms.num(2) == 0 {
+            if self.randoms.num(2)? == 0 {
                 (y2, y1) = (y1, y2)
             }
 
             let mut pb = PathBuilder::new();
             pb.move_to(x1 as f32, y1 as f32);
 
-            if self.randoms.num(2) == 0 {
+            if self.randoms.num(2)? == 0 {
                 // 二阶曲线
                 pb.quad_to(cx as f32, cy as f32, x2 as f32, y2 as f32);
             } else {
                 // 三阶曲线
-                let cx1 = self.randoms.num_between(self.width / 4, self.width / 4 * 3);
-                let cy1 = self.randoms.num_between(5, self.height - 5);
+                let cx1 = self
+                    .randoms
+                    .num_between(self.width / 4, self.width / 4 * 3)?;
+                let cy1 = self.randoms.num_between(5, self.height - 5)?;
                 pb.cubic_to(
                     cx as f32, cy as f32, cx1 as f32, cy1 as f32, x2 as f32, y2 as f32,
                 );
@@ -284,23 +563,157 @@ impl Captcha {
                 &draw_options,
             );
         }
+
+        Ok(())
     }
 
-    pub fn get_font(&mut self) -> Arc<Font> {
-        if let Some(font) = font::get_font(&self.font_name) {
-            font
-        } else {
-            font::get_font(self.font_names[0]).unwrap()
+    /// 随机画干扰圆点
+    pub fn draw_dot(
+        &mut self,
+        num: usize,
+        g: &mut DrawTarget,
+        color: Option<Color>,
+    ) -> Result<(), EmptyRange> {
+        for _ in 0..num {
+            let color = color.clone().unwrap_or_else(|| self.color());
+            let color: raqote::Color = color.into();
+
+            let r = 1 + self.randoms.num(2)?;
+            let x = self.randoms.num_between(0, self.width)?;
+            let y = self.randoms.num_between(0, self.height)?;
+
+            let mut pb = PathBuilder::new();
+            pb.arc(x as f32, y as f32, r as f32, 0., 2. * std::f32::consts::PI);
+            let path = pb.finish();
+
+            g.fill(
+                &path,
+                &Source::Solid(SolidSource::from(color)),
+                &DrawOptions::new(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 按配置画干扰圆、贝塞尔曲线、干扰线和圆点，各验证码类型可以指定干扰圆和贝塞尔曲线的绘制选项
+    pub fn draw_noise(
+        &mut self,
+        g: &mut DrawTarget,
+        oval_options: DrawOptions,
+        mut curve_style: StrokeStyle,
+        curve_options: DrawOptions,
+    ) -> Result<(), EmptyRange> {
+        let noise = self.noise;
+        if let Some(width) = noise.stroke_width {
+            curve_style.width = width;
         }
+
+        self.draw_oval_with_option(noise.ovals, g, None, oval_options)?;
+        self.draw_bessel_line_with_all_option(noise.curves, g, None, curve_style, curve_options)?;
+        self.draw_line(noise.lines, g, None)?;
+        self.draw_dot(noise.dots, g, None)
+    }
+
+    /// 按配置随机将像素置为黑色或白色（椒盐噪点）
+    pub fn draw_speckle(&mut self, g: &mut DrawTarget) -> Result<(), EmptyRange> {
+        let data = g.get_data_mut();
+        let count = (data.len() as f32 * self.noise.speckle) as usize;
+        for _ in 0..count {
+            let i = self.randoms.num(data.len())?;
+            data[i] = if self.randoms.num(2)? == 0 {
+                0xff000000
+            } else {
+                0xffffffff
+            };
+        }
+
+        Ok(())
+    }
+
+    pub fn get_font(&mut self) -> Result<Rc<Font>, FontError> {
+        self.get_font_at(0)
+    }
+
+    /// 获取第`index`个字符使用的字体，字体无法加载时使用默认字体
+    pub fn get_font_at(&mut self, index: usize) -> Result<Rc<Font>, FontError> {
+        self.fonts[index % self.fonts.len()]
+            .load()
+            .or_else(|| font::get_font(CaptchaFont::Font1.file_name()))
+            .ok_or_else(|| FontError::NotFound(CaptchaFont::Font1.file_name().into()))
+    }
+
+    /// 获取用于估算字符宽度的样本字形。未指定样本字符时，取字符集中字体能画出的最宽的字符，
+    /// 因此只包含自定义字符集的字体（如西里尔字母字体）也能使用
+    fn sample_glyph(&self, font: &Font) -> Result<u32, FontError> {
+        if let Some(c) = self.sample_char {
+            return Self::glyph_for_char(font, c);
+        }
+
+        let advance = |glyph: u32| font.advance(glyph).map_or(0., |advance| advance.x());
+        self.charset
+            .chars()
+            .iter()
+            .filter_map(|c| font.glyph_for_char(*c))
+            .map(|glyph| (glyph, advance(glyph)))
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(glyph, _)| glyph)
+            .ok_or(FontError::MissingGlyph(self.charset.chars()[0]))
+    }
+
+    /// 获取字符在字体中的字形，没有时返回错误
+    pub fn glyph_for_char(font: &Font, c: char) -> Result<u32, FontError> {
+        font.glyph_for_char(c).ok_or(FontError::MissingGlyph(c))
+    }
+
+    /// 计算字形在当前字体大小下的栅格宽度和高度
+    pub fn glyph_size(&self, font: &Font, glyph: u32) -> Result<(i32, i32), Error> {
+        let bounds = font
+            .raster_bounds(
+                glyph,
+                self.font_size,
+                Default::default(),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .map_err(|e| Error::Rendering(e.to_string()))?;
+        Ok((bounds.width(), bounds.height()))
     }
 
     pub fn get_font_size(&mut self) -> f32 {
         self.font_size
     }
 
+    /// 按名称设置字体，名称可以是内置字体的文件名，也可以是系统字体的字体族名
+    pub fn set_font_by_name(&mut self, font_name: &str, size: Option<f32>) {
+        self.fonts = vec![FontSource::named(font_name)];
+        self.font_size = size.unwrap_or(32.);
+    }
+
+    /// 设置字体，有多个字体时每个字符依次轮换使用；为空时使用默认字体
+    pub fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.fonts = if fonts.is_empty() {
+            vec![CaptchaFont::Font1.into()]
+        } else {
+            fonts
+        };
+    }
+
+    /// 设置字体大小
+    pub fn set_font_size(&mut self, size: f32) {
+        self.font_size = size;
+    }
+
+    /// 使用系统中的中文字体，并以汉字的宽度估算字符间距
+    pub fn set_cjk_font(&mut self, size: Option<f32>) {
+        if let Some(font_name) = font::cjk_font_name() {
+            self.set_font_by_name(font_name, size);
+        }
+        self.sample_char = Some('国');
+    }
+
     pub fn set_font_by_enum(&mut self, font: CaptchaFont, size: Option<f32>) {
-        let font_name = self.font_names[font as usize];
-        self.font_name = font_name.into();
+        self.fonts = vec![font.into()];
         self.font_size = size.unwrap_or(32.);
     }
 }
@@ -329,8 +742,8 @@ where
     /// 特别地/In particular:
     ///
     /// - 对算术验证码[ArithmeticCaptcha](crate::captcha::arithmetic::ArithmeticCaptcha)而言，这里的`len`是验证码中数字的数量。
-    /// For [ArithmeticCaptcha](crate::captcha::arithmetic::ArithmeticCaptcha), the `len` presents the count of the digits
-    /// in the Captcha.
+    ///   For [ArithmeticCaptcha](crate::captcha::arithmetic::ArithmeticCaptcha), the `len` presents the count of the digits
+    ///   in the Captcha.
     fn with_size_and_len(width: i32, height: i32, len: usize) -> Self;
 
     /// 使用完整的参数来初始化，包括输出图像大小、验证码字符长度和输出字体及其大小
@@ -341,6 +754,21 @@ where
     /// 关于`len`字段的注意事项，请参见[with_size_and_len](Self::with_size_and_len)中的说明。Refer to the document of
     /// [with_size_and_len](Self::with_size_and_len) for the precautions of the `len` property.
     fn with_all(width: i32, height: i32, len: usize, font: CaptchaFont, font_size: f32) -> Self;
+
+    /// 使用指定的随机数生成器初始化，其余参数使用默认值
+    ///
+    /// Initialize the Captcha with the specified random number generator, and the default properties.
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self;
+
+    /// 使用随机数种子初始化，其余参数使用默认值。相同的种子会生成完全相同的验证码图像，可用于测试或复现问题。
+    /// 由种子生成的验证码是可预测的，请勿在生产环境中使用。
+    ///
+    /// Initialize the Captcha with a random seed, and the default properties. The same seed always produces the
+    /// identical Captcha image, which is useful for tests and reproducing problems. Captchas generated from a seed
+    /// are predictable, so don't use it in production.
+    fn with_seed(seed: u64) -> Self {
+        Self::with_rng(ChaCha20Rng::seed_from_u64(seed))
+    }
 }
 
 impl NewCaptcha for Captcha {
@@ -363,37 +791,35 @@ impl NewCaptcha for Captcha {
         .map(|v| (*v).into())
         .collect();
 
-        let font_names = [
-            "actionj.ttf",
-            "epilog.ttf",
-            "fresnel.ttf",
-            "headache.ttf",
-            "lexo.ttf",
-            "prefix.ttf",
-            "progbot.ttf",
-            "ransom.ttf",
-            "robot.ttf",
-            "scandal.ttf",
-        ];
-
-        let font_name = font_names[0].into();
+        let fonts = vec![CaptchaFont::Font1.into()];
         let font_size = 32.;
+        let sample_char = None;
         let len = 5;
         let width = 130;
         let height = 48;
-        let char_type = CaptchaType::TypeDefault;
+        let charset = Charset::default();
+        let background = Background::default();
+        let glyph_transform = GlyphTransform::none();
+        let glyph_layout = GlyphLayout::cells();
+        let warp = Warp::none();
+        let noise = Noise::default();
         let chars = None;
 
         Self {
             randoms: Randoms::new(),
             color,
-            font_names,
-            font_name,
+            fonts,
             font_size,
+            sample_char,
             len,
             width,
             height,
-            char_type,
+            charset,
+            background,
+            glyph_transform,
+            glyph_layout,
+            warp,
+            noise,
             chars,
         }
     }
@@ -422,22 +848,25 @@ impl NewCaptcha for Captcha {
         _self.font_size = font_size;
         _self
     }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        let mut _self = Self::new();
+        _self.randoms = Randoms::with_rng(rng);
+        _self
+    }
 }
 
 /// 验证码的抽象方法  Traits which a Captcha must implements.
 pub trait AbstractCaptcha: NewCaptcha {
-    /// 错误类型
-    type Error: std::error::Error + Debug + Send + Sync + 'static;
-
     /// 输出验证码到指定位置
     ///
     /// Write the Captcha image to the specified place.
-    fn out(&mut self, out: impl Write) -> Result<(), Self::Error>;
+    fn out(&mut self, out: impl Write) -> Result<(), Error>;
 
     /// 获取验证码中的字符（即正确答案）
     ///
     /// Get the characters (i.e. the correct answer) of the Captcha
-    fn get_chars(&mut self) -> Vec<char>;
+    fn get_chars(&mut self) -> Result<Vec<char>, Error>;
 
     /// 输出Base64编码。注意，返回值会带编码头（例如`data:image/png;base64,`），可以直接在浏览器中显示；如不需要编码头，
     /// 请使用[base64_with_head](Self::base64_with_head)方法并传入空参数以去除编码头。
@@ -445,17 +874,158 @@ pub trait AbstractCaptcha: NewCaptcha {
     /// Get the Base64 encoded image. Reminds: the returned Base64 strings will begin with an encoding head like
     /// `data:image/png;base64,`, which make it possible to display in browsers directly. If you don't need it, you may
     /// use [base64_with_head](Self::base64_with_head) and pass a null string.
-    fn base64(&mut self) -> Result<String, Self::Error>;
+    fn base64(&mut self) -> Result<String, Error>;
 
     /// 获取验证码的MIME类型
     ///
     /// Get the MIME Content type of the Captcha.
     fn get_content_type(&mut self) -> String;
 
+    /// 设置验证码的文本类型，默认为[TypeDefault](CaptchaType::TypeDefault)（字母数字混合）。已生成的验证码会被清除。
+    ///
+    /// 算术验证码和中文验证码不使用该设置。
+    ///
+    /// Set the character type of the Captcha, default as [TypeDefault](CaptchaType::TypeDefault) (numbers and letters).
+    /// The generated Captcha will be cleared.
+    ///
+    /// Arithmetic and Chinese Captcha do not use this setting.
+    fn set_char_type(&mut self, char_type: CaptchaType);
+
+    /// 以链式调用的方式设置验证码的文本类型
+    ///
+    /// Set the character type of the Captcha in a chained call.
+    fn with_char_type(mut self, char_type: CaptchaType) -> Self {
+        self.set_char_type(char_type);
+        self
+    }
+
+    /// 设置验证码的字符集，可以使用自定义的字符。已生成的验证码会被清除。
+    ///
+    /// 算术验证码和中文验证码不使用该设置。
+    ///
+    /// Set the character set of the Captcha, which may consist of custom characters. The generated Captcha will be
+    /// cleared.
+    ///
+    /// Arithmetic and Chinese Captcha do not use this setting.
+    fn set_charset(&mut self, charset: Charset);
+
+    /// 以链式调用的方式设置验证码的字符集
+    ///
+    /// Set the character set of the Captcha in a chained call.
+    fn with_charset(mut self, charset: Charset) -> Self {
+        self.set_charset(charset);
+        self
+    }
+
+    /// 设置验证码使用的字体，可以是内置字体、系统字体或由文件、数据加载的字体
+    ///
+    /// Set the font used by the Captcha, which may be a built-in font, a system font, or a font loaded from a file or
+    /// data.
+    fn set_font(&mut self, font: impl Into<FontSource>) {
+        self.set_fonts(vec![font.into()])
+    }
+
+    /// 设置验证码使用的多个字体，每个字符依次轮换使用其中的字体；为空时使用默认字体
+    ///
+    /// Set several fonts used by the Captcha; characters rotate among them in turn. The default font is used if
+    /// `fonts` is empty.
+    fn set_fonts(&mut self, fonts: Vec<FontSource>);
+
+    /// 设置验证码的字体大小
+    ///
+    /// Set the font size of the Captcha.
+    fn set_font_size(&mut self, size: f32);
+
+    /// 设置背景，默认为白色，参见[Background]。GIF验证码的每一帧使用相同的背景。
+    ///
+    /// Set the background, which is white by default; see [Background]. Every frame of a GIF Captcha shares the same
+    /// background.
+    fn set_background(&mut self, background: Background);
+
+    /// 以链式调用的方式设置背景
+    ///
+    /// Set the background in a chained call.
+    fn with_background(mut self, background: Background) -> Self {
+        self.set_background(background);
+        self
+    }
+
+    /// 设置字符的随机变换（旋转、缩放和倾斜），默认不做变换，参见[GlyphTransform]
+    ///
+    /// Set the random transform (rotation, scale and skew) of the characters, which is off by default; see
+    /// [GlyphTransform].
+    fn set_glyph_transform(&mut self, transform: GlyphTransform);
+
+    /// 以链式调用的方式设置字符的随机变换
+    ///
+    /// Set the random transform of the characters in a chained call.
+    fn with_glyph_transform(mut self, transform: GlyphTransform) -> Self {
+        self.set_glyph_transform(transform);
+        self
+    }
+
+    /// 设置字符的排列方式，默认每个字符占据一个等宽的格子，参见[GlyphLayout]
+    ///
+    /// Set the layout of the characters, one equal-width cell per character by default; see [GlyphLayout].
+    fn set_glyph_layout(&mut self, layout: GlyphLayout);
+
+    /// 以链式调用的方式设置字符的排列方式
+    ///
+    /// Set the layout of the characters in a chained call.
+    fn with_glyph_layout(mut self, layout: GlyphLayout) -> Self {
+        self.set_glyph_layout(layout);
+        self
+    }
+
+    /// 设置图像的波浪扭曲，默认不做扭曲，参见[Warp]。GIF验证码的每一帧使用相同的扭曲。
+    ///
+    /// Set the wave warp of the image, which is off by default; see [Warp]. Every frame of a GIF Captcha is warped the
+    /// same way.
+    fn set_warp(&mut self, warp: Warp);
+
+    /// 以链式调用的方式设置图像的波浪扭曲
+    ///
+    /// Set the wave warp of the image in a chained call.
+    fn with_warp(mut self, warp: Warp) -> Self {
+        self.set_warp(warp);
+        self
+    }
+
+    /// 设置干扰元素，默认为两个干扰圆和一条贝塞尔曲线，参见[Noise]
+    ///
+    /// Set the noise drawn on the image, two ovals and one Bézier curve by default; see [Noise].
+    fn set_noise(&mut self, noise: Noise);
+
+    /// 以链式调用的方式设置干扰元素
+    ///
+    /// Set the noise drawn on the image in a chained call.
+    fn with_noise(mut self, noise: Noise) -> Self {
+        self.set_noise(noise);
+        self
+    }
+
+    /// 设置验证码使用的随机数生成器。已生成的验证码会被清除。
+    ///
+    /// 验证码的答案属于安全凭据，生产环境中请使用密码学安全的随机数生成器；默认使用以系统熵源初始化的ChaCha20。
+    ///
+    /// Set the random number generator used by the Captcha. The generated Captcha will be cleared.
+    ///
+    /// Captcha answers are security tokens, so please use a cryptographically secure generator in production; ChaCha20
+    /// seeded from the system entropy source is used by default.
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static);
+
+    /// 设置随机数种子，相同的种子会生成完全相同的验证码图像。已生成的验证码会被清除。
+    ///
+    /// Set the random seed; the same seed always produces the identical Captcha image. The generated Captcha will be
+    /// cleared.
+    fn set_seed(&mut self, seed: u64) {
+        self.set_rng(ChaCha20Rng::seed_from_u64(seed))
+    }
+
     /// 输出Base64编码（指定编码头）
     ///
     /// Get the Base64 encoded image, with specified encoding head.
-    fn base64_with_head(&mut self, head: &str) -> Result<String, Self::Error> {
+    fn base64_with_head(&mut self, head: &str) -> Result<String, Error> {
         let mut output_stream = Vec::new();
         self.out(&mut output_stream)?;
         Ok(String::from(head) + &BASE64_STANDARD.encode(&output_stream))
@@ -0,0 +1,140 @@
+//! 验证码字符集 Character sets of the Captcha
+
+use crate::base::captcha::CaptchaType;
+
+/// 数字，去除了0、1等容易混淆的数字
+const NUMBERS: &str = "23456789";
+
+/// 大写字母，去除了I、L、O等容易混淆的字母
+const UPPERS: &str = "ABCDEFGHJKMNPQRSTUVWXYZ";
+
+/// 小写字母，去除了i、l、o等容易混淆的字母
+const LOWERS: &str = "abcdefghjkmnpqrstuvwxyz";
+
+/// 验证码字符集 The character set which the Captcha samples from
+///
+/// 字符集可以由任意可见字符构成，例如去除某些字体中容易混淆的字符，或使用西里尔字母；重复的字符会被去除。
+/// 各个[CaptchaType]也对应着预定义的字符集。字体中必须包含字符集中所有字符的字形，否则输出验证码时会返回
+/// [MissingGlyph](crate::FontError::MissingGlyph)错误；内置字体只包含拉丁字母和数字。
+///
+/// A character set can be built from arbitrary visible characters, e.g. removing characters which are easy to confuse
+/// in some font, or using Cyrillic letters; duplicated characters are removed. Each [CaptchaType] maps to a predefined
+/// character set as well. The font must contain glyphs for every character in the set, otherwise writing the Captcha
+/// returns a [MissingGlyph](crate::FontError::MissingGlyph) error; the built-in fonts only cover Latin letters and
+/// digits.
+///
+/// ```
+/// use easy_captcha::{CaptchaType, Charset};
+///
+/// let cyrillic = Charset::new("БГДЖЗИЛПФЦЧШЫЭЮЯ".chars()).unwrap();
+/// assert_eq!(cyrillic.len(), 16);
+/// assert_eq!(cyrillic.entropy(5), 20.0);
+///
+/// let kiosk = Charset::from(CaptchaType::TypeNumAndUpper).without("7SZ").unwrap();
+/// assert!(!kiosk.chars().contains(&'S'));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Charset {
+    chars: Vec<char>,
+}
+
+impl Charset {
+    /// 由给定的字符构造字符集；字符集为空，或包含空白字符、控制字符等无法绘制的字符时返回`None`
+    ///
+    /// Build a character set from the given characters; returns `None` if the set is empty or contains characters which
+    /// can't be drawn, like whitespace or control characters.
+    pub fn new(chars: impl IntoIterator<Item = char>) -> Option<Self> {
+        let mut set = Vec::new();
+        for c in chars {
+            if c.is_whitespace() || c.is_control() {
+                return None;
+            }
+            if !set.contains(&c) {
+                set.push(c);
+            }
+        }
+
+        if set.is_empty() {
+            None
+        } else {
+            Some(Self { chars: set })
+        }
+    }
+
+    /// 去除字符集中的指定字符；去除后字符集为空时返回`None`
+    ///
+    /// Remove the specified characters from the character set; returns `None` if nothing is left.
+    pub fn without(&self, excluded: &str) -> Option<Self> {
+        Self::new(
+            self.chars
+                .iter()
+                .copied()
+                .filter(|c| !excluded.contains(*c)),
+        )
+    }
+
+    /// 字符集中的全部字符
+    ///
+    /// All the characters in the set.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// 字符集中的字符数量
+    ///
+    /// The count of characters in the set.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// 指定长度的验证码的熵（单位为比特），即`len * log2(字符数)`
+    ///
+    /// The entropy in bits of a Captcha with the specified length, i.e. `len * log2(count of characters)`.
+    pub fn entropy(&self, len: usize) -> f64 {
+        len as f64 * (self.chars.len() as f64).log2()
+    }
+
+    /// 字符集是否为空；由于字符集无法由空集构造，总是返回`false`
+    ///
+    /// Whether the set is empty; always `false` as a character set can't be built from nothing.
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+}
+
+impl Default for Charset {
+    fn default() -> Self {
+        CaptchaType::TypeDefault.into()
+    }
+}
+
+impl From<CaptchaType> for Charset {
+    fn from(value: CaptchaType) -> Self {
+        let chars: String = match value {
+            CaptchaType::TypeDefault => [NUMBERS, UPPERS, LOWERS].concat(),
+            CaptchaType::TypeOnlyNumber => NUMBERS.into(),
+            CaptchaType::TypeOnlyChar => [UPPERS, LOWERS].concat(),
+            CaptchaType::TypeOnlyUpper => UPPERS.into(),
+            CaptchaType::TypeOnlyLower => LOWERS.into(),
+            CaptchaType::TypeNumAndUpper => [NUMBERS, UPPERS].concat(),
+        };
+
+        Self {
+            chars: chars.chars().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_rejects_characters_which_cant_be_drawn() {
+        assert_eq!(Charset::new("AAB".chars()).unwrap().chars(), &['A', 'B']);
+        assert!(Charset::new("".chars()).is_none());
+        assert!(Charset::new(" ".chars()).is_none());
+        assert!(Charset::new("AB\tC".chars()).is_none());
+        assert!(Charset::new("AB\u{7}".chars()).is_none());
+    }
+}
@@ -0,0 +1,99 @@
+//! 验证码的错误类型 Errors of the Captcha
+
+use crate::base::font::FontError;
+use crate::base::randoms::EmptyRange;
+use std::fmt::{Debug, Display, Formatter};
+
+/// 验证码产生的错误 Errors occurred when generating or verifying a Captcha
+///
+/// 所有公开的方法都以该类型返回错误，而不是直接panic，便于在接口中将错误映射为合适的状态码：[InvalidConfig](Error::InvalidConfig)
+/// 通常意味着调用方的配置有误，其余错误则属于服务端的错误。
+///
+/// All the public methods return errors as this type instead of panicking, so that handlers can map them to proper
+/// status codes: [InvalidConfig](Error::InvalidConfig) usually means the configuration given by the caller is wrong,
+/// while the others are server-side errors.
+#[derive(Debug)]
+pub enum Error {
+    /// 图像编码或写入输出失败
+    ///
+    /// Failed to encode the image or write it to the output.
+    Encoding(Box<dyn std::error::Error + Send + Sync>),
+
+    /// 字体无法加载，或字体中缺少所需的字形
+    ///
+    /// The font can't be loaded, or it lacks a required glyph.
+    Font(FontError),
+
+    /// 绘制验证码图像失败
+    ///
+    /// Failed to draw the Captcha image.
+    Rendering(String),
+
+    /// 验证码的配置无效，例如尺寸过小或长度为0
+    ///
+    /// The configuration of the Captcha is invalid, e.g. the size is too small or the length is 0.
+    InvalidConfig(String),
+
+    /// 算术验证码的算式无法生成或计算
+    ///
+    /// The arithmetic problem can't be generated or evaluated.
+    Arithmetic(String),
+
+    /// 保存或读取验证码信息失败，例如Session出错
+    ///
+    /// Failed to save or load the Captcha information, e.g. the session fails.
+    Storage(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Encoding(e) => write!(f, "Unable to encode the Captcha image: {}", e),
+            Error::Font(e) => Display::fmt(e, f),
+            Error::Rendering(e) => write!(f, "Unable to draw the Captcha image: {}", e),
+            Error::InvalidConfig(e) => write!(f, "Invalid Captcha configuration: {}", e),
+            Error::Arithmetic(e) => write!(f, "Unable to generate the arithmetic problem: {}", e),
+            Error::Storage(e) => write!(f, "Unable to store the Captcha: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Encoding(e) | Error::Storage(e) => Some(e.as_ref()),
+            Error::Font(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FontError> for Error {
+    fn from(value: FontError) -> Self {
+        Error::Font(value)
+    }
+}
+
+impl From<EmptyRange> for Error {
+    fn from(value: EmptyRange) -> Self {
+        Error::InvalidConfig(value.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::Encoding(Box::new(value))
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(value: png::EncodingError) -> Self {
+        Error::Encoding(Box::new(value))
+    }
+}
+
+impl From<gif::EncodingError> for Error {
+    fn from(value: gif::EncodingError) -> Self {
+        Error::Encoding(Box::new(value))
+    }
+}
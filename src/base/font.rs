@@ -0,0 +1,201 @@
+//! 验证码字体 Fonts of the Captcha
+
+use crate::base::captcha::CaptchaFont;
+use crate::utils::font;
+use font_kit::font::Font;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 用户字体的编号，用于区分不同的字体数据
+static NEXT_FONT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 加载字体时产生的错误 Errors occurred when loading a font
+#[derive(Debug)]
+pub enum FontError {
+    /// 读取字体文件失败
+    ///
+    /// Failed to read the font file.
+    Io(std::io::Error),
+
+    /// 字体数据无法解析
+    ///
+    /// The font data can't be parsed.
+    InvalidData(String),
+
+    /// 系统中找不到指定的字体族
+    ///
+    /// The specified font family is not found in the system.
+    NotFound(String),
+
+    /// 字体中没有所需字符的字形
+    ///
+    /// The font contains no glyph for the required character.
+    MissingGlyph(char),
+}
+
+impl Display for FontError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "Unable to read the font file: {}", e),
+            FontError::InvalidData(e) => write!(f, "Unable to parse the font data: {}", e),
+            FontError::NotFound(family) => write!(f, "Unable to find the font family {}.", family),
+            FontError::MissingGlyph(c) => write!(f, "The font contains no glyph for {:?}.", c),
+        }
+    }
+}
+
+impl Error for FontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FontError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// 验证码字体来源 The source of a font used by the Captcha
+///
+/// 字体可以是内置字体、系统字体，也可以由字体文件或字体数据加载，以便使用自己的品牌字体。
+///
+/// A font may be a built-in font, a system font, or loaded from a font file or font data, so that you can use your
+/// own brand typeface.
+///
+/// 解析后的字体按线程缓存：每个线程第一次使用某个字体来源时解析一次，之后的渲染直接复用。请复用同一个`FontSource`
+/// （克隆开销很小），而不要每次渲染都重新创建，否则每次都会重新解析字体。
+///
+/// Parsed fonts are cached per thread: each thread parses a font source the first time it uses it and reuses it for
+/// later renders. Reuse the same `FontSource` (cloning is cheap) instead of creating one per render, or the font is
+/// parsed again every time.
+///
+/// ```no_run
+/// use easy_captcha::captcha::spec::SpecCaptcha;
+/// use easy_captcha::{AbstractCaptcha, CaptchaFont, FontSource, NewCaptcha};
+///
+/// let mut captcha = SpecCaptcha::new();
+/// captcha.set_fonts(vec![
+///     FontSource::from_path("/path/to/brand.ttf").unwrap(),
+///     FontSource::from_system("DejaVu Sans").unwrap(),
+///     CaptchaFont::Font3.into(),
+/// ]);
+/// ```
+#[derive(Clone)]
+pub struct FontSource {
+    inner: Source,
+}
+
+#[derive(Clone)]
+enum Source {
+    /// 内置字体的文件名或系统字体的字体族名
+    Named(String),
+
+    /// 用户提供的字体数据
+    Data { id: usize, data: Arc<Vec<u8>> },
+}
+
+impl FontSource {
+    /// 使用内置字体
+    ///
+    /// Use a built-in font.
+    pub fn builtin(font: CaptchaFont) -> Self {
+        Self::named(font.file_name())
+    }
+
+    /// 由字体数据加载字体，支持TTF和OTF格式
+    ///
+    /// Load a font from the font data; TTF and OTF are supported.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, FontError> {
+        let data = Arc::new(bytes);
+        Font::from_bytes(data.clone(), 0).map_err(|e| FontError::InvalidData(e.to_string()))?;
+
+        Ok(Self {
+            inner: Source::Data {
+                id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed),
+                data,
+            },
+        })
+    }
+
+    /// 由字体文件加载字体
+    ///
+    /// Load a font from the font file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, FontError> {
+        Self::from_bytes(std::fs::read(path).map_err(FontError::Io)?)
+    }
+
+    /// 按字体族名使用系统中安装的字体
+    ///
+    /// Use a font installed in the system by its family name.
+    pub fn from_system(family_name: &str) -> Result<Self, FontError> {
+        match font::load_system_font(family_name) {
+            Some(_) => Ok(Self::named(family_name)),
+            None => Err(FontError::NotFound(family_name.into())),
+        }
+    }
+
+    /// 内置字体的文件名或系统字体的字体族名，系统字体在渲染时才查找
+    pub(crate) fn named(font_name: &str) -> Self {
+        Self {
+            inner: Source::Named(font_name.into()),
+        }
+    }
+
+    /// 加载字体
+    pub(crate) fn load(&self) -> Option<Rc<Font>> {
+        match &self.inner {
+            Source::Named(name) => font::get_font(name),
+            Source::Data { id, data } => font::get_font_from_data(*id, data.clone()),
+        }
+    }
+}
+
+impl Debug for FontSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.inner {
+            Source::Named(name) => f.debug_tuple("FontSource").field(name).finish(),
+            Source::Data { id, data } => f
+                .debug_struct("FontSource")
+                .field("id", id)
+                .field("len", &data.len())
+                .finish(),
+        }
+    }
+}
+
+impl From<CaptchaFont> for FontSource {
+    fn from(value: CaptchaFont) -> Self {
+        Self::builtin(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::spec::SpecCaptcha;
+    use crate::{AbstractCaptcha, NewCaptcha};
+
+    #[test]
+    fn it_loads_user_fonts() {
+        let font = FontSource::from_path("resources/lexo.ttf").unwrap();
+        assert!(font.load().is_some());
+
+        let mut captcha = SpecCaptcha::new();
+        captcha.set_fonts(vec![font, CaptchaFont::Font8.into()]);
+        captcha.out(Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn it_rejects_invalid_fonts() {
+        assert!(matches!(
+            FontSource::from_bytes(b"not a font".to_vec()),
+            Err(FontError::InvalidData(_))
+        ));
+        assert!(matches!(
+            FontSource::from_path("resources/missing.ttf"),
+            Err(FontError::Io(_))
+        ));
+    }
+}
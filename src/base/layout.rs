@@ -0,0 +1,188 @@
+//! 字符的排列方式 Layout of the characters
+
+use crate::base::randoms::Randoms;
+use font_kit::font::Font;
+use raqote::{Point, Transform};
+use std::rc::Rc;
+
+/// 字符重叠比例的上限，避免字符完全重合而无法辨认
+const MAX_OVERLAP: f32 = 0.5;
+
+/// 字符的排列方式 Layout of the characters
+///
+/// 默认情况下图像被等分为与字符数量相同的格子，每个字符居中放在自己的格子里，字符之间总有空隙，很容易被逐个切分识别。
+/// 紧凑排列按字体中每个字形实际的步进宽度依次排列字符，相邻字符按给定的比例相互重叠，并在垂直方向上随机抖动。
+/// 重叠比例在0到0.5之间，超出的部分会被截断。
+///
+/// [Default]提供了一组推荐的参数：紧凑排列、重叠20%、垂直抖动±4像素。
+///
+/// By default the image is split into as many cells as characters and each character is centered in its own cell, so
+/// there is always a gap between the characters, which makes them easy to segment. The condensed layout places the
+/// characters one after another by the real advance of each glyph in the font, overlapping neighbours by the given
+/// ratio and jittering them vertically at random. The overlap ratio is between 0 and 0.5; values beyond are clamped.
+///
+/// [Default] provides a recommended set of parameters: condensed, 20% overlap and ±4 pixels of vertical jitter.
+///
+/// ```
+/// use easy_captcha::captcha::spec::SpecCaptcha;
+/// use easy_captcha::{AbstractCaptcha, GlyphLayout, NewCaptcha};
+///
+/// let mut captcha = SpecCaptcha::new().with_glyph_layout(GlyphLayout::default().with_overlap(0.3));
+/// captcha.out(Vec::new()).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphLayout {
+    /// 是否紧凑排列
+    condensed: bool,
+
+    /// 相邻字符的重叠比例
+    overlap: f32,
+
+    /// 最大垂直抖动（像素）
+    jitter: f32,
+}
+
+impl GlyphLayout {
+    /// 等分格子排列，字符间距由字符集中最宽的字符决定
+    ///
+    /// One cell per character; the spacing is decided by the widest character of the charset.
+    pub fn cells() -> Self {
+        Self {
+            condensed: false,
+            overlap: 0.,
+            jitter: 0.,
+        }
+    }
+
+    /// 按字形的步进宽度紧凑排列，不重叠也不抖动
+    ///
+    /// Place the characters by their advances, without overlap or jitter.
+    pub fn condensed() -> Self {
+        Self {
+            condensed: true,
+            ..Self::cells()
+        }
+    }
+
+    /// 设置相邻字符的重叠比例（相对于字形的步进宽度），并使用紧凑排列。比例为NaN时无法算出字符的位置，整个调用不生效
+    ///
+    /// Set the overlap ratio of neighbouring characters, relative to the glyph advance, and use the condensed layout.
+    /// A NaN ratio can't position the characters, so the call has no effect.
+    pub fn with_overlap(mut self, ratio: f32) -> Self {
+        if !ratio.is_nan() {
+            self.condensed = true;
+            self.overlap = ratio.clamp(0., MAX_OVERLAP);
+        }
+        self
+    }
+
+    /// 设置最大垂直抖动（像素），字符会在正负该距离之间随机上下移动，并使用紧凑排列。
+    /// 抖动范围必须是有限的，传入NaN或无穷大时整个调用不生效
+    ///
+    /// Set the maximum vertical jitter in pixels; characters are moved randomly up or down within it. The condensed
+    /// layout is used. The jitter range must be finite, so NaN or infinity makes the call have no effect.
+    pub fn with_jitter(mut self, pixels: f32) -> Self {
+        if pixels.is_finite() {
+            self.condensed = true;
+            self.jitter = pixels.abs();
+        }
+        self
+    }
+
+    /// 是否等分格子排列
+    pub(crate) fn is_cells(&self) -> bool {
+        !self.condensed
+    }
+
+    /// 按步进宽度紧凑排列，返回每个字符起点的横坐标和垂直抖动。抖动为0时不抽取随机数，之后的颜色和干扰元素与格子排列时相同
+    pub(crate) fn condense(
+        &self,
+        advances: &[f32],
+        width: f32,
+        randoms: &mut Randoms,
+    ) -> Vec<(f32, f32)> {
+        let step = 1. - self.overlap;
+        let total = match advances.split_last() {
+            Some((last, rest)) => rest.iter().map(|a| a * step).sum::<f32>() + last,
+            None => 0.,
+        };
+
+        let mut x = ((width - total) / 2.).max(0.);
+        advances
+            .iter()
+            .map(|advance| {
+                let dy = if self.jitter > 0. {
+                    randoms.float_between(-self.jitter, self.jitter)
+                } else {
+                    0.
+                };
+                let placed = (x, dy);
+                x += advance * step;
+                placed
+            })
+            .collect()
+    }
+}
+
+impl Default for GlyphLayout {
+    fn default() -> Self {
+        Self::condensed().with_overlap(0.2).with_jitter(4.)
+    }
+}
+
+/// 一个字符在图像中的位置
+pub(crate) struct GlyphPlacement {
+    /// 字符使用的字体
+    pub(crate) font: Rc<Font>,
+
+    /// 字符在字体中的字形
+    pub(crate) glyph: u32,
+
+    /// 字形在基线上的起点
+    pub(crate) origin: Point,
+
+    /// 以字符中心为原点的随机变换
+    pub(crate) transform: Transform,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_overlaps_neighbours_by_the_ratio() {
+        let layout = GlyphLayout::cells().with_overlap(0.25);
+        assert!(!layout.is_cells());
+        assert_eq!(GlyphLayout::cells().with_overlap(2.).overlap, MAX_OVERLAP);
+
+        // 总宽度为 20*0.75*2 + 20 = 50，居中后从25开始
+        let placed = layout.condense(&[20., 20., 20.], 100., &mut Randoms::new());
+        assert_eq!(placed, vec![(25., 0.), (40., 0.), (55., 0.)]);
+
+        // 超出图像宽度时从左边开始
+        let placed = layout.condense(&[80., 80.], 100., &mut Randoms::new());
+        assert_eq!(placed[0].0, 0.);
+    }
+
+    #[test]
+    fn it_ignores_nan_overlap_and_unbounded_jitter() {
+        let layout = GlyphLayout::cells();
+        assert_eq!(layout.with_overlap(f32::NAN), layout);
+        assert_eq!(layout.with_jitter(f32::NAN), layout);
+        assert_eq!(layout.with_jitter(f32::INFINITY), layout);
+        assert_eq!(layout.with_overlap(f32::INFINITY).overlap, MAX_OVERLAP);
+
+        let layout = GlyphLayout::default();
+        assert_eq!(layout.with_overlap(f32::NAN), layout);
+        assert_eq!(layout.with_jitter(f32::NEG_INFINITY), layout);
+    }
+
+    #[test]
+    fn it_jitters_within_bounds() {
+        let layout = GlyphLayout::condensed().with_jitter(-3.);
+        let mut randoms = Randoms::new();
+        for (_, dy) in layout.condense(&[10.; 100], 130., &mut randoms) {
+            assert!((-3.0..=3.).contains(&dy));
+        }
+    }
+}
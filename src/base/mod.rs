@@ -1,4 +1,12 @@
 //! Base traits
 
+pub(crate) mod background;
 pub(crate) mod captcha;
+pub(crate) mod charset;
+pub(crate) mod error;
+pub(crate) mod font;
+pub(crate) mod layout;
+pub(crate) mod noise;
 pub(crate) mod randoms;
+pub(crate) mod transform;
+pub(crate) mod warp;
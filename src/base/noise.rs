@@ -0,0 +1,140 @@
+//! 干扰元素 Noise of the Captcha image
+
+/// 椒盐噪点密度的上限
+const MAX_SPECKLE: f32 = 0.2;
+
+/// 干扰元素的配置 Configuration of the noise drawn on the Captcha
+///
+/// 可以分别设置干扰线、干扰圆、贝塞尔曲线和圆点的数量，以及黑白椒盐噪点占像素总数的比例（不超过0.2）。
+/// 默认与以往的效果一致：两个干扰圆和一条贝塞尔曲线。
+///
+/// The counts of lines, ovals, Bézier curves and dots can be set respectively, as well as the ratio of black and white
+/// salt-and-pepper speckles to all the pixels (at most 0.2). The default is the same as before: two ovals and one
+/// Bézier curve.
+///
+/// ```
+/// use easy_captcha::captcha::spec::SpecCaptcha;
+/// use easy_captcha::{AbstractCaptcha, NewCaptcha, Noise};
+///
+/// let noise = Noise::default().with_lines(2).with_dots(30).with_speckle(0.02);
+/// let mut captcha = SpecCaptcha::new().with_noise(noise);
+/// captcha.out(Vec::new()).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Noise {
+    /// 干扰线数量
+    pub(crate) lines: usize,
+
+    /// 干扰圆数量
+    pub(crate) ovals: usize,
+
+    /// 贝塞尔曲线数量
+    pub(crate) curves: usize,
+
+    /// 圆点数量
+    pub(crate) dots: usize,
+
+    /// 椒盐噪点占像素总数的比例
+    pub(crate) speckle: f32,
+
+    /// 干扰线、干扰圆和贝塞尔曲线的线宽，为空时使用各验证码类型的默认线宽
+    pub(crate) stroke_width: Option<f32>,
+}
+
+impl Noise {
+    /// 不画任何干扰元素
+    ///
+    /// No noise at all.
+    pub fn none() -> Self {
+        Self {
+            lines: 0,
+            ovals: 0,
+            curves: 0,
+            dots: 0,
+            speckle: 0.,
+            stroke_width: None,
+        }
+    }
+
+    /// 设置干扰线数量
+    ///
+    /// Set the count of straight lines.
+    pub fn with_lines(mut self, count: usize) -> Self {
+        self.lines = count;
+        self
+    }
+
+    /// 设置干扰圆数量
+    ///
+    /// Set the count of ovals.
+    pub fn with_ovals(mut self, count: usize) -> Self {
+        self.ovals = count;
+        self
+    }
+
+    /// 设置贝塞尔曲线数量
+    ///
+    /// Set the count of Bézier curves.
+    pub fn with_curves(mut self, count: usize) -> Self {
+        self.curves = count;
+        self
+    }
+
+    /// 设置圆点数量
+    ///
+    /// Set the count of dots.
+    pub fn with_dots(mut self, count: usize) -> Self {
+        self.dots = count;
+        self
+    }
+
+    /// 设置椒盐噪点占像素总数的比例，范围为0到0.2，传入NaN时密度不变
+    ///
+    /// Set the ratio of salt-and-pepper speckles to all the pixels, between 0 and 0.2; NaN leaves the density as it is.
+    pub fn with_speckle(mut self, density: f32) -> Self {
+        if !density.is_nan() {
+            self.speckle = density.clamp(0., MAX_SPECKLE);
+        }
+        self
+    }
+
+    /// 设置干扰线、干扰圆和贝塞尔曲线的线宽。线宽必须是有限的数，否则仍沿用之前的线宽
+    ///
+    /// Set the stroke width of the lines, ovals and Bézier curves. The width must be a finite number; otherwise the
+    /// previous width stays in use.
+    pub fn with_stroke_width(mut self, width: f32) -> Self {
+        if width.is_finite() {
+            self.stroke_width = Some(width.abs());
+        }
+        self
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::none().with_ovals(2).with_curves(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_defaults_to_the_classic_noise() {
+        let noise = Noise::default();
+        assert_eq!((noise.lines, noise.ovals, noise.curves), (0, 2, 1));
+        assert_eq!((noise.dots, noise.speckle), (0, 0.));
+        assert_eq!(noise.with_speckle(5.).speckle, MAX_SPECKLE);
+    }
+
+    #[test]
+    fn it_ignores_non_finite_widths_and_densities() {
+        let noise = Noise::default().with_stroke_width(2.).with_speckle(0.05);
+        assert_eq!(noise.with_stroke_width(f32::NAN), noise);
+        assert_eq!(noise.with_stroke_width(f32::INFINITY), noise);
+        assert_eq!(noise.with_stroke_width(f32::NEG_INFINITY), noise);
+        assert_eq!(noise.with_speckle(f32::NAN), noise);
+        assert_eq!(Noise::none().with_stroke_width(f32::NAN).stroke_width, None);
+    }
+}
@@ -1,86 +1,104 @@
+use crate::base::charset::Charset;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// 中文验证码使用的常用汉字
+pub(crate) const HAN: &str = concat!(
+    "的是我不在们有来他这上着个地到大里说就去子得也和那要下看天时过出小么起你都把好还",
+    "多没为又可家学只以主会样年想生同老中从自面前头道它后然走很像见两用她国动进成回什",
+    "边作对开而己些现山民候经发工向事命给长水几义声于高手知理眼志点心战问但身方实吃做",
+    "叫当住听打呢真全才四已所之最光产情路分总条白话东席次亲如被花口放儿常气五第使写军",
+    "吧文运再果怎定许快明行因别飞外树物活部门无往船望新带队先力完却站代员机更九您每风",
+    "级跟笑啊孩万少直意夜比阶连车重便斗马哪化太指变社似士者干石满日决百原拿群究各六本",
+    "思解立河村难早论吗根共让相研今其书坐接应关信觉步反处记将千找争领或师结块跑谁草越",
+    "字加脚紧爱等习阵怕月青半火法题建赶位唱海七女任件感准张团屋离色脸片科倒睛利世刚且",
+    "由送切星导晚表够整认响雪流未场该并底深刻平伟忙提确近亮轻讲农古黑告界拉名呀土清阳",
+    "照办史改历转画造嘴此治北必服雨穿内识验传业菜爬睡兴形量咱观苦体众通冲合破友度术饭",
+    "公旁房极南枪读沙岁线野坚空收算至政城劳落钱特围弟胜教热展包歌类渐强数乡呼性音答哥",
+    "际旧神座章帮啦受系令跳非何牛取岸敢掉忽种装顶急林停息句区衣般报叶压慢叔背细",
+);
+
+/// 随机数范围为空时产生的错误，例如验证码尺寸过小，无法放下干扰图形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EmptyRange {
+    pub min: i64,
+    pub max: i64,
+}
 
-use rand::{random};
+impl Display for EmptyRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot sample from the empty range [{}, {}); the Captcha may be too small.",
+            self.min, self.max
+        )
+    }
+}
 
+impl Error for EmptyRange {}
 
 /// 随机数工具类
+///
+/// 默认使用以系统熵源初始化的ChaCha20作为密码学安全的随机数生成器，所有的范围采样均是均匀无偏的。
 pub(crate) struct Randoms {
-    /// 定义验证码字符.去除了0、O、I、L等容易混淆的字母
-    pub alpha: [char; 54],
-
-    /// 数字的最大索引，不包括最大值
-    pub num_max_index: usize,
-
-    /// 字符的最小索引，包括最小值
-    pub char_min_index: usize,
-
-    /// 字符的最大索引，不包括最大值
-    pub char_max_index: usize,
-
-    /// 大写字符最小索引
-    pub upper_min_index: usize,
-
-    /// 大写字符最大索引
-    pub upper_max_index: usize,
-
-    /// 小写字母最小索引
-    pub lower_min_index: usize,
-
-    /// 小写字母最大索引
-    pub lower_max_index: usize,
+    /// 随机数生成器
+    rng: Box<dyn RngCore + Send>,
 }
 
 impl Randoms {
     pub fn new() -> Self {
-        // Defines the Captcha characters, removing characters like 0, O, I, l, etc.
-        let alpha = [
-            '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'J',
-            'K', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'a', 'b', 'c',
-            'd', 'e', 'f', 'g', 'h', 'j', 'k', 'm', 'n', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w',
-            'x', 'y', 'z',
-        ];
+        Self::with_rng(ChaCha20Rng::from_entropy())
+    }
 
-        let num_max_index = 8;
-        let char_min_index = num_max_index;
-        let char_max_index = alpha.len();
-        let upper_min_index = char_min_index;
-        let upper_max_index = upper_min_index + 23;
-        let lower_min_index = upper_max_index;
-        let lower_max_index = char_max_index;
+    /// 使用指定的随机数生成器
+    pub fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        Self { rng: Box::new(rng) }
+    }
 
-        Self {
-            alpha,
-            num_max_index,
-            char_min_index,
-            char_max_index,
-            upper_min_index,
-            upper_max_index,
-            lower_min_index,
-            lower_max_index,
+    /// 产生两个数之间的随机数，包括min，不包括max
+    pub fn num_between(&mut self, min: i32, max: i32) -> Result<i32, EmptyRange> {
+        if min >= max {
+            return Err(EmptyRange {
+                min: min as i64,
+                max: max as i64,
+            });
         }
+        Ok(self.rng.gen_range(min..max))
     }
 
-    /// 产生两个数之间的随机数
-    pub fn num_between(&mut self, min: i32, max: i32) -> i32 {
-        min + (random::<usize>() % (max - min) as usize) as i32
+    /// 产生两个浮点数之间的随机数，包括min和max
+    pub fn float_between(&mut self, min: f32, max: f32) -> f32 {
+        if min >= max {
+            return min;
+        }
+        self.rng.gen_range(min..=max)
     }
 
     /// 产生0-num的随机数,不包括num
-    pub fn num(&mut self, num: usize) -> usize {
-        random::<usize>() % num
+    pub fn num(&mut self, num: usize) -> Result<usize, EmptyRange> {
+        if num == 0 {
+            return Err(EmptyRange { min: 0, max: 0 });
+        }
+        Ok(self.rng.gen_range(0..num))
     }
 
-    /// 返回ALPHA中的随机字符
-    pub fn alpha(&mut self) -> char {
-        self.alpha[self.num(self.alpha.len())]
+    /// 返回字符集中的随机字符
+    pub fn alpha(&mut self, charset: &Charset) -> char {
+        // 字符集不会为空
+        charset.chars()[self.rng.gen_range(0..charset.len())]
     }
 
-    /// 返回ALPHA中第0位到第num位的随机字符
-    pub fn alpha_under(&mut self, num: usize) -> char {
-        self.alpha[self.num(num)]
+    /// 返回常用汉字中的随机字符
+    pub fn alpha_han(&mut self) -> char {
+        let count = HAN.chars().count();
+        HAN.chars().nth(self.rng.gen_range(0..count)).unwrap()
     }
 
-    /// 返回ALPHA中第min位到第max位的随机字符
-    pub fn alpha_between(&mut self, min: usize, max: usize) -> char {
-        self.alpha[self.num_between(min as i32, max as i32) as usize]
+    /// 从非空的列表中随机选择一项
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        items.choose(&mut self.rng)
     }
 }
@@ -0,0 +1,220 @@
+//! 字符的随机变换 Random transforms of the characters
+
+use crate::base::randoms::Randoms;
+use raqote::Transform;
+
+/// 旋转角度的上限（度）
+const MAX_ROTATION: f32 = 30.;
+
+/// 缩放比例的下限
+const MIN_SCALE: f32 = 0.7;
+
+/// 缩放比例的上限
+const MAX_SCALE: f32 = 1.3;
+
+/// 水平错切系数的上限，约相当于倾斜22度
+const MAX_SHEAR: f32 = 0.4;
+
+/// 字符的随机变换 Random transforms applied to each character
+///
+/// 默认情况下字符都端正地排列在同一基线上，很容易被OCR识别。设置变换后，每个字符会在给定的范围内随机旋转、缩放和倾斜。
+/// 为保证字符清晰可辨，旋转角度不超过30度，缩放比例在0.7到1.3之间，错切系数不超过0.4，超出的部分会被截断。
+/// 参数不是有限数时无法截断到上述范围，该次设置不生效。变换后超出图像的字符会被平移回图像内。
+///
+/// [Default]提供了一组推荐的范围：旋转±15度、缩放0.85到1.15、错切±0.2。
+///
+/// By default the characters stand upright on the same baseline, which OCR reads easily. With a transform, each
+/// character is randomly rotated, scaled and skewed within the given bounds. To keep the characters legible, the
+/// rotation is at most 30 degrees, the scale is between 0.7 and 1.3, and the shear factor is at most 0.4; values
+/// beyond are clamped. A parameter that isn't a finite number can't be clamped into these bounds, so that call has no
+/// effect. A transformed character reaching beyond the image is moved back inside.
+///
+/// [Default] provides a recommended set of bounds: ±15 degrees of rotation, 0.85 to 1.15 scale and ±0.2 shear.
+///
+/// ```
+/// use easy_captcha::captcha::spec::SpecCaptcha;
+/// use easy_captcha::{AbstractCaptcha, GlyphTransform, NewCaptcha};
+///
+/// let mut captcha = SpecCaptcha::new().with_glyph_transform(GlyphTransform::default().with_rotation(25.));
+/// captcha.out(Vec::new()).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphTransform {
+    /// 最大旋转角度（度）
+    rotation: f32,
+
+    /// 最小缩放比例
+    min_scale: f32,
+
+    /// 最大缩放比例
+    max_scale: f32,
+
+    /// 最大水平错切系数
+    shear: f32,
+}
+
+impl GlyphTransform {
+    /// 不做任何变换，字符保持端正，渲染结果与未引入变换时完全相同
+    ///
+    /// No transform at all; the characters stay upright and render exactly as they did before transforms existed.
+    pub fn none() -> Self {
+        Self {
+            rotation: 0.,
+            min_scale: 1.,
+            max_scale: 1.,
+            shear: 0.,
+        }
+    }
+
+    /// 设置最大旋转角度（度），字符会在正负该角度之间随机旋转
+    ///
+    /// Set the maximum rotation in degrees; characters are rotated randomly between plus and minus this angle.
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        if !degrees.is_finite() {
+            return self;
+        }
+        self.rotation = degrees.abs().min(MAX_ROTATION);
+        self
+    }
+
+    /// 设置缩放比例的范围，字符会在该范围内随机缩放
+    ///
+    /// Set the range of the scale; characters are scaled randomly within it.
+    pub fn with_scale(mut self, min: f32, max: f32) -> Self {
+        if !min.is_finite() || !max.is_finite() {
+            return self;
+        }
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        self.min_scale = min.clamp(MIN_SCALE, MAX_SCALE);
+        self.max_scale = max.clamp(MIN_SCALE, MAX_SCALE);
+        self
+    }
+
+    /// 设置最大水平错切系数，字符会在正负该系数之间随机倾斜
+    ///
+    /// Set the maximum horizontal shear factor; characters are skewed randomly between plus and minus this factor.
+    pub fn with_shear(mut self, factor: f32) -> Self {
+        if !factor.is_finite() {
+            return self;
+        }
+        self.shear = factor.abs().min(MAX_SHEAR);
+        self
+    }
+
+    /// 是否不做任何变换
+    pub(crate) fn is_none(&self) -> bool {
+        *self == Self::none()
+    }
+
+    /// 为一个字符随机生成变换
+    pub(crate) fn sample(&self, randoms: &mut Randoms) -> GlyphDistortion {
+        GlyphDistortion {
+            rotation: randoms
+                .float_between(-self.rotation, self.rotation)
+                .to_radians(),
+            scale: randoms.float_between(self.min_scale, self.max_scale),
+            shear: randoms.float_between(-self.shear, self.shear),
+        }
+    }
+}
+
+impl Default for GlyphTransform {
+    fn default() -> Self {
+        Self::none()
+            .with_rotation(15.)
+            .with_scale(0.85, 1.15)
+            .with_shear(0.2)
+    }
+}
+
+/// 一个字符实际使用的变换
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GlyphDistortion {
+    /// 旋转角度（弧度）
+    rotation: f32,
+
+    /// 缩放比例
+    scale: f32,
+
+    /// 水平错切系数
+    shear: f32,
+}
+
+impl GlyphDistortion {
+    /// 不做任何变换
+    pub(crate) const IDENTITY: Self = Self {
+        rotation: 0.,
+        scale: 1.,
+        shear: 0.,
+    };
+
+    /// 以`(cx, cy)`为中心的变换矩阵
+    pub(crate) fn around(&self, cx: f32, cy: f32) -> Transform {
+        let (sin, cos) = self.rotation.sin_cos();
+        Transform::translation(-cx, -cy)
+            .then(&Transform::scale(self.scale, self.scale))
+            .then(&Transform::new(1., 0., self.shear, 1., 0., 0.))
+            .then(&Transform::new(cos, sin, -sin, cos, 0., 0.))
+            .then(&Transform::translation(cx, cy))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_clamps_to_legible_bounds() {
+        let transform = GlyphTransform::none()
+            .with_rotation(-90.)
+            .with_scale(2., 0.1)
+            .with_shear(1.);
+        assert_eq!(transform.rotation, MAX_ROTATION);
+        assert_eq!(
+            (transform.min_scale, transform.max_scale),
+            (MIN_SCALE, MAX_SCALE)
+        );
+        assert_eq!(transform.shear, MAX_SHEAR);
+
+        let mut randoms = Randoms::new();
+        for _ in 0..100 {
+            let d = transform.sample(&mut randoms);
+            assert!(d.rotation.abs() <= MAX_ROTATION.to_radians());
+            assert!((MIN_SCALE..=MAX_SCALE).contains(&d.scale));
+            assert!(d.shear.abs() <= MAX_SHEAR);
+        }
+    }
+
+    #[test]
+    fn it_ignores_non_finite_values() {
+        let transform = GlyphTransform::default();
+        let ignored = transform
+            .with_rotation(f32::NAN)
+            .with_scale(f32::NAN, 1.2)
+            .with_scale(0.9, f32::INFINITY)
+            .with_shear(f32::NEG_INFINITY);
+        assert_eq!(ignored, transform);
+
+        let mut randoms = Randoms::new();
+        let d = ignored.sample(&mut randoms);
+        assert!(d.rotation.is_finite() && d.scale.is_finite() && d.shear.is_finite());
+    }
+
+    #[test]
+    fn it_keeps_the_center_in_place() {
+        let distortion = GlyphDistortion {
+            rotation: 0.3,
+            scale: 1.2,
+            shear: 0.2,
+        };
+        let center = distortion
+            .around(40., 20.)
+            .transform_point(raqote::Point::new(40., 20.));
+        assert!((center.x - 40.).abs() < 1e-4 && (center.y - 20.).abs() < 1e-4);
+
+        assert_eq!(
+            GlyphDistortion::IDENTITY.around(40., 20.),
+            Transform::identity()
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! 图像的波浪扭曲 Wave warp of the Captcha image
+
+use crate::base::randoms::Randoms;
+use raqote::DrawTarget;
+use std::f32::consts::TAU;
+
+/// 最小波长（像素），避免波纹过密导致字符无法辨认
+const MIN_WAVELENGTH: f32 = 8.;
+
+/// 鱼眼强度的上限
+const MAX_FISHEYE: f32 = 0.5;
+
+/// 图像的波浪扭曲 Wave warp applied to the rendered image
+///
+/// 绘制完成后，图像会沿x轴和y轴按正弦波扭曲，相位和振幅随机生成；还可以叠加鱼眼效果，放大图像中心。
+/// 振幅在给定值的一半到给定值之间随机选取，波长不小于8像素，鱼眼强度在0到0.5之间，超出的部分会被截断。
+/// 为NaN或无穷大的振幅、波长或鱼眼强度无法算出取色的位置，会被直接忽略。
+///
+/// [Default]提供了一组推荐的参数：振幅3像素、波长40像素、不使用鱼眼。
+///
+/// After drawing, the image is warped along the x and y axes by sine waves with random phases and amplitudes; a
+/// fisheye effect magnifying the center may be added as well. The amplitude is picked randomly between half the given
+/// value and the given value, the wavelength is at least 8 pixels, and the fisheye strength is between 0 and 0.5;
+/// values beyond are clamped. A NaN or infinite amplitude, wavelength or fisheye strength can't locate the source
+/// pixels, so it is simply ignored.
+///
+/// [Default] provides a recommended set of parameters: 3 pixels of amplitude, 40 pixels of wavelength and no fisheye.
+///
+/// ```
+/// use easy_captcha::captcha::gif::GifCaptcha;
+/// use easy_captcha::{AbstractCaptcha, NewCaptcha, Warp};
+///
+/// let mut captcha = GifCaptcha::new().with_warp(Warp::default().with_fisheye(0.3));
+/// captcha.out(Vec::new()).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Warp {
+    /// 最大振幅（像素）
+    amplitude: f32,
+
+    /// 波长（像素）
+    wavelength: f32,
+
+    /// 鱼眼强度
+    fisheye: f32,
+}
+
+impl Warp {
+    /// 不做任何扭曲，每个像素都留在绘制时的位置
+    ///
+    /// No warp at all; every pixel stays where it was drawn.
+    pub fn none() -> Self {
+        Self {
+            amplitude: 0.,
+            wavelength: 40.,
+            fisheye: 0.,
+        }
+    }
+
+    /// 设置最大振幅（像素）
+    ///
+    /// Set the maximum amplitude in pixels.
+    pub fn with_amplitude(mut self, pixels: f32) -> Self {
+        if !pixels.is_finite() {
+            return self;
+        }
+        self.amplitude = pixels.abs();
+        self
+    }
+
+    /// 设置波长（像素）
+    ///
+    /// Set the wavelength in pixels.
+    pub fn with_wavelength(mut self, pixels: f32) -> Self {
+        if !pixels.is_finite() {
+            return self;
+        }
+        self.wavelength = pixels.abs().max(MIN_WAVELENGTH);
+        self
+    }
+
+    /// 设置鱼眼强度，0表示不使用鱼眼
+    ///
+    /// Set the fisheye strength; 0 means no fisheye.
+    pub fn with_fisheye(mut self, strength: f32) -> Self {
+        if !strength.is_finite() {
+            return self;
+        }
+        self.fisheye = strength.clamp(0., MAX_FISHEYE);
+        self
+    }
+
+    /// 为一张图像随机生成扭曲参数，不做扭曲时返回`None`
+    pub(crate) fn sample(&self, randoms: &mut Randoms) -> Option<WarpParams> {
+        if self.amplitude == 0. && self.fisheye == 0. {
+            return None;
+        }
+
+        Some(WarpParams {
+            amplitude_x: randoms.float_between(self.amplitude / 2., self.amplitude),
+            amplitude_y: randoms.float_between(self.amplitude / 2., self.amplitude),
+            phase_x: randoms.float_between(0., TAU),
+            phase_y: randoms.float_between(0., TAU),
+            wavelength: self.wavelength,
+            fisheye: self.fisheye,
+        })
+    }
+}
+
+impl Default for Warp {
+    fn default() -> Self {
+        Self::none().with_amplitude(3.).with_wavelength(40.)
+    }
+}
+
+/// 一张图像实际使用的扭曲参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct WarpParams {
+    amplitude_x: f32,
+    amplitude_y: f32,
+    phase_x: f32,
+    phase_y: f32,
+    wavelength: f32,
+    fisheye: f32,
+}
+
+impl WarpParams {
+    /// 扭曲绘制好的图像
+    pub(crate) fn apply(&self, dt: &mut DrawTarget) {
+        let (width, height) = (dt.width() as usize, dt.height() as usize);
+        let source = dt.get_data().to_vec();
+
+        let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+        let radius = (cx * cx + cy * cy).sqrt();
+
+        let data = dt.get_data_mut();
+        for y in 0..height {
+            for x in 0..width {
+                // 每个目标像素从源图像中扭曲后的位置取色
+                let (fx, fy) = (x as f32, y as f32);
+                let mut sx =
+                    fx + self.amplitude_x * (TAU * fy / self.wavelength + self.phase_x).sin();
+                let mut sy =
+                    fy + self.amplitude_y * (TAU * fx / self.wavelength + self.phase_y).sin();
+
+                if self.fisheye > 0. {
+                    let (dx, dy) = (sx - cx, sy - cy);
+                    let r = (dx * dx + dy * dy).sqrt() / radius;
+                    let factor = 1. - self.fisheye * (1. - r * r);
+                    sx = cx + dx * factor;
+                    sy = cy + dy * factor;
+                }
+
+                data[y * width + x] = bilinear(&source, width, height, sx, sy);
+            }
+        }
+    }
+}
+
+/// 以双线性插值在预乘ARGB图像中取色，超出边界的位置取边缘的颜色
+fn bilinear(source: &[u32], width: usize, height: usize, x: f32, y: f32) -> u32 {
+    let x = x.clamp(0., (width - 1) as f32);
+    let y = y.clamp(0., (height - 1) as f32);
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let pixel = |x: usize, y: usize| source[y * width + x];
+    let (p00, p10, p01, p11) = (pixel(x0, y0), pixel(x1, y0), pixel(x0, y1), pixel(x1, y1));
+
+    (0..4).fold(0, |result, channel| {
+        let shift = channel * 8;
+        let c = |p: u32| ((p >> shift) & 0xff) as f32;
+        let top = c(p00) + (c(p10) - c(p00)) * tx;
+        let bottom = c(p01) + (c(p11) - c(p01)) * tx;
+        let value = (top + (bottom - top) * ty).round() as u32;
+        result | (value.min(255) << shift)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raqote::{DrawOptions, SolidSource, Source};
+
+    #[test]
+    fn it_samples_within_bounds() {
+        assert!(Warp::none().sample(&mut Randoms::new()).is_none());
+
+        let warp = Warp::none()
+            .with_amplitude(-6.)
+            .with_wavelength(1.)
+            .with_fisheye(2.);
+        assert_eq!(warp.wavelength, MIN_WAVELENGTH);
+        assert_eq!(warp.fisheye, MAX_FISHEYE);
+
+        let mut randoms = Randoms::new();
+        for _ in 0..100 {
+            let params = warp.sample(&mut randoms).unwrap();
+            assert!((3.0..=6.).contains(&params.amplitude_x));
+            assert!((3.0..=6.).contains(&params.amplitude_y));
+        }
+    }
+
+    #[test]
+    fn it_ignores_non_finite_values() {
+        let warp = Warp::default();
+        let ignored = warp
+            .with_amplitude(f32::NAN)
+            .with_wavelength(f32::INFINITY)
+            .with_fisheye(f32::NAN);
+        assert_eq!(ignored, warp);
+
+        let params = ignored.sample(&mut Randoms::new()).unwrap();
+        assert!(params.amplitude_x.is_finite() && params.amplitude_y.is_finite());
+    }
+
+    #[test]
+    fn it_warps_the_pixels() {
+        let mut dt = DrawTarget::new(40, 20);
+        dt.fill_rect(
+            10.,
+            0.,
+            10.,
+            20.,
+            &Source::Solid(SolidSource::from_unpremultiplied_argb(255, 255, 0, 0)),
+            &DrawOptions::new(),
+        );
+        let original = dt.get_data().to_vec();
+
+        let still = WarpParams {
+            amplitude_x: 0.,
+            amplitude_y: 0.,
+            phase_x: 1.,
+            phase_y: 2.,
+            wavelength: 40.,
+            fisheye: 0.,
+        };
+        still.apply(&mut dt);
+        assert_eq!(dt.get_data(), &original[..]);
+
+        WarpParams {
+            amplitude_x: 4.,
+            ..still
+        }
+        .apply(&mut dt);
+        assert_ne!(dt.get_data(), &original[..]);
+    }
+}
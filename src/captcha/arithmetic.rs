@@ -3,15 +3,20 @@
 //! PNG格式算术验证码
 //!
 
-use crate::base::captcha::AbstractCaptcha;
+use crate::base::captcha::{AbstractCaptcha, Captcha};
+use crate::base::randoms::Randoms;
 
 use crate::captcha::spec::SpecCaptcha;
-use crate::{CaptchaFont, NewCaptcha};
+use crate::{
+    Background, CaptchaFont, CaptchaType, Charset, Error, FontSource, GlyphLayout, GlyphTransform,
+    NewCaptcha, Noise, Warp,
+};
 
+use rand::RngCore;
 use std::io::Write;
-use std::ops::Add;
 
 /// 算术验证码中使用的符号
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq)]
 enum Symbol {
     /// 操作数
@@ -82,10 +87,8 @@ impl From<usize> for Symbol {
     }
 }
 
-/// 算数验证码
-pub struct ArithmeticCaptcha {
-    pub(crate) spec: SpecCaptcha,
-
+/// 算式的生成器，由算术验证码的不同实现共用
+pub(crate) struct Arithmetic {
     /// 计算公式
     arithmetic_string: Option<String>,
 
@@ -96,22 +99,45 @@ pub struct ArithmeticCaptcha {
     algorithm_sign: usize,
 }
 
-impl ArithmeticCaptcha {
-    /// 生成一个算式
-    ///
-    /// Generate a new arithmetic problem.
-    pub fn alphas(&mut self) -> Vec<char> {
-        let len = self.spec.captcha.len;
-        let ref mut randoms = self.spec.captcha.randoms;
+impl Arithmetic {
+    pub fn new() -> Self {
+        Self {
+            arithmetic_string: None,
+            difficulty: 10,
+            algorithm_sign: 4,
+        }
+    }
+
+    /// 生成一个算式，并将计算结果保存到验证码中
+    pub fn alphas(&mut self, captcha: &mut Captcha) -> Result<Vec<char>, Error> {
+        if captcha.len == 0 {
+            return Err(Error::InvalidConfig(
+                "The arithmetic problem contains no numbers.".into(),
+            ));
+        }
 
-        let mut arithmetic_list = Vec::new();
-        arithmetic_list.reserve(len + len - 1);
+        let arithmetic = self
+            .arithmetic_list(&mut captcha.randoms, captcha.len)?
+            .join("");
+        let result = evalexpr::eval(&arithmetic.replace('x', "*").replace('÷', "/"))
+            .map_err(|e| Error::Arithmetic(e.to_string()))?
+            .to_string();
+
+        self.arithmetic_string = Some(arithmetic + "=?");
+        captcha.chars = Some(result.clone());
+
+        Ok(result.chars().collect())
+    }
+
+    /// 生成算式中的各个操作数和符号
+    fn arithmetic_list(&self, randoms: &mut Randoms, len: usize) -> Result<Vec<String>, Error> {
+        let mut arithmetic_list = Vec::with_capacity(len + len - 1);
 
         let mut last_symbol = None;
         let mut div_amount = 0;
 
         for i in 0..len {
-            let mut number = randoms.num(self.difficulty);
+            let mut number = randoms.num(self.difficulty)?;
 
             // 如果上一步生成的为除号，要重新设置除数和被除数，确保难度满足设定要求且可以整除
             if let Some(Symbol::DIV { .. }) = last_symbol {
@@ -119,27 +145,26 @@ impl ArithmeticCaptcha {
                 // 避免被除数为 0
                 number = if number == 0 { 1 } else { number };
                 arithmetic_list[2 * (i - 1)] =
-                    (number * randoms.num((self.difficulty as f64).sqrt() as usize)).to_string();
+                    (number * randoms.num((self.difficulty as f64).sqrt() as usize)?).to_string();
             }
 
             // 如果是减法则获取一个比第一个小的数据
             if let Some(Symbol::SUB { .. }) = last_symbol {
-                let ref first_num = arithmetic_list[0];
-                number = randoms.num(first_num.parse::<usize>().unwrap() + 1);
+                let first_num = arithmetic_list[0]
+                    .parse::<usize>()
+                    .map_err(|e| Error::Arithmetic(e.to_string()))?;
+                number = randoms.num(first_num + 1)?;
             }
 
             arithmetic_list.push(number.to_string());
 
             if i < len - 1 {
-                let _type: Symbol;
-
                 // 除法只出现一次，否则还需要递归更新除数，第一个除数将会很大
-                if div_amount == 1 {
-                    _type =
-                        (randoms.num_between(1, self.algorithm_sign as i32 - 1) as usize).into();
+                let _type: Symbol = if div_amount == 1 {
+                    (randoms.num_between(1, self.algorithm_sign as i32 - 1)? as usize).into()
                 } else {
-                    _type = (randoms.num_between(1, self.algorithm_sign as i32) as usize).into();
-                }
+                    (randoms.num_between(1, self.algorithm_sign as i32)? as usize).into()
+                };
 
                 match _type {
                     Symbol::NUM { .. } => { /* 不可达 */ }
@@ -158,60 +183,92 @@ impl ArithmeticCaptcha {
             }
         }
 
-        self.arithmetic_string = Some(arithmetic_list.join(""));
-        self.spec.captcha.chars = Some(
-            evalexpr::eval(
-                self.arithmetic_string
-                    .clone()
-                    .unwrap()
-                    .replace("x", "*")
-                    .replace("÷", "/")
-                    .as_str(),
-            )
-            .unwrap()
-            .to_string(),
-        );
-        self.arithmetic_string = Some(self.arithmetic_string.clone().unwrap().add("=?"));
+        Ok(arithmetic_list)
+    }
+
+    /// 获取算式的字符串，没有则立即生成
+    pub fn get_arithmetic_string(&mut self, captcha: &mut Captcha) -> Result<String, Error> {
+        if self.arithmetic_string.is_none() || captcha.chars.is_none() {
+            self.alphas(captcha)?;
+        }
 
-        self.spec.captcha.chars.clone().unwrap().chars().collect()
+        Ok(self.arithmetic_string.clone().unwrap_or_default())
     }
 
-    /// 获取算式的字符串
-    ///
-    /// Get the String of the arithmetic problem
-    pub fn get_arithmetic_string(&mut self) -> String {
-        if self.arithmetic_string.is_none() {
-            self.alphas();
+    /// 获取绘制到图像上的算式字符，字体中没有除号时使用斜线代替
+    pub fn display_chars(&mut self, captcha: &mut Captcha) -> Result<Vec<char>, Error> {
+        let mut chars: Vec<char> = self.get_arithmetic_string(captcha)?.chars().collect();
+        for (i, c) in chars.iter_mut().enumerate() {
+            if *c == '÷' && captcha.get_font_at(i)?.glyph_for_char('÷').is_none() {
+                *c = '/';
+            }
         }
 
-        self.arithmetic_string.clone().unwrap()
+        Ok(chars)
     }
 
-    /// 设置算术的难度；默认值为10
-    ///
-    /// Set the difficulty of the arithmetic problem; default as 10.
+    /// 获取计算结果，没有则立即生成
+    pub fn get_chars(&mut self, captcha: &mut Captcha) -> Result<Vec<char>, Error> {
+        if self.arithmetic_string.is_none() || captcha.chars.is_none() {
+            self.alphas(captcha)?;
+        }
+        Ok(captcha.text_char())
+    }
+
+    /// 设置算术的难度
     pub fn set_difficulty(&mut self, difficulty: usize) {
         // 做上下界检测，避免越界
-        if difficulty <= 0 {
+        if difficulty == 0 {
             self.difficulty = 10
         } else {
             self.difficulty = difficulty
         }
     }
 
+    /// 设置包含的算术符号
+    pub fn support_algorithm_sign(&mut self, algorithm_sign: usize) {
+        // 做上下界检测，避免越界
+        self.algorithm_sign = algorithm_sign.clamp(2, 5)
+    }
+}
+
+/// 算数验证码
+pub struct ArithmeticCaptcha {
+    pub(crate) spec: SpecCaptcha,
+
+    /// 算式生成器
+    arithmetic: Arithmetic,
+}
+
+impl ArithmeticCaptcha {
+    /// 生成一个算式
+    ///
+    /// Generate a new arithmetic problem.
+    pub fn alphas(&mut self) -> Result<Vec<char>, Error> {
+        self.arithmetic.alphas(&mut self.spec.captcha)
+    }
+
+    /// 获取算式的字符串
+    ///
+    /// Get the String of the arithmetic problem
+    pub fn get_arithmetic_string(&mut self) -> Result<String, Error> {
+        self.arithmetic
+            .get_arithmetic_string(&mut self.spec.captcha)
+    }
+
+    /// 设置算术的难度；默认值为10
+    ///
+    /// Set the difficulty of the arithmetic problem; default as 10.
+    pub fn set_difficulty(&mut self, difficulty: usize) {
+        self.arithmetic.set_difficulty(difficulty)
+    }
+
     /// 设置包含的算术符号。可选2~5，难度依次由低到高（加-减-乘-除）
     ///
     /// Set the arithmetic symbol used in the arithmetic problem; Available from 2 to 5, which includes Plus, Minus,
     /// Multiply and Divide.
     pub fn support_algorithm_sign(&mut self, algorithm_sign: usize) {
-        // 做上下界检测，避免越界
-        self.algorithm_sign = if algorithm_sign < 2 {
-            2
-        } else if algorithm_sign > 5 {
-            5
-        } else {
-            algorithm_sign
-        }
+        self.arithmetic.support_algorithm_sign(algorithm_sign)
     }
 }
 
@@ -220,15 +277,9 @@ impl NewCaptcha for ArithmeticCaptcha {
         let mut spec = SpecCaptcha::new();
         spec.captcha.len = 2;
 
-        let arithmetic_string = None;
-        let difficulty = 10;
-        let algorithm_sign = 4;
-
         Self {
             spec,
-            arithmetic_string,
-            difficulty,
-            algorithm_sign,
+            arithmetic: Arithmetic::new(),
         }
     }
 
@@ -255,48 +306,125 @@ impl NewCaptcha for ArithmeticCaptcha {
         sf.spec.captcha.set_font_by_enum(font, Some(font_size));
         sf
     }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        let mut sf = Self::new();
+        sf.spec.captcha.set_rng(rng);
+        sf
+    }
 }
 
 impl AbstractCaptcha for ArithmeticCaptcha {
-    type Error = png::EncodingError;
-
-    fn out(&mut self, out: impl Write) -> Result<(), Self::Error> {
-        if self.arithmetic_string.is_none() {
-            self.alphas();
-        }
-
-        self.spec.graphics_image(
-            &self.arithmetic_string.clone().unwrap().chars().collect(),
-            out,
-        )
+    fn out(&mut self, out: impl Write) -> Result<(), Error> {
+        let arithmetic_string = self.arithmetic.display_chars(&mut self.spec.captcha)?;
+        self.spec.graphics_image(&arithmetic_string, out)
     }
 
-    fn get_chars(&mut self) -> Vec<char> {
-        if self.arithmetic_string.is_none() {
-            self.alphas();
-        }
-        self.spec.captcha.chars.clone().unwrap().chars().collect()
+    fn get_chars(&mut self) -> Result<Vec<char>, Error> {
+        self.arithmetic.get_chars(&mut self.spec.captcha)
     }
 
-    fn base64(&mut self) -> Result<String, Self::Error> {
-        self.spec.base64_with_head("data:image/png;base64,")
+    fn base64(&mut self) -> Result<String, Error> {
+        self.base64_with_head("data:image/png;base64,")
     }
 
     fn get_content_type(&mut self) -> String {
         "image/png".into()
     }
+
+    fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.spec.captcha.set_char_type(char_type)
+    }
+
+    fn set_charset(&mut self, charset: Charset) {
+        self.spec.captcha.set_charset(charset)
+    }
+
+    fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.spec.captcha.set_fonts(fonts)
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.spec.captcha.set_font_size(size)
+    }
+
+    fn set_background(&mut self, background: Background) {
+        self.spec.captcha.set_background(background)
+    }
+
+    fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.spec.captcha.set_glyph_transform(transform)
+    }
+
+    fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.spec.captcha.set_glyph_layout(layout)
+    }
+
+    fn set_warp(&mut self, warp: Warp) {
+        self.spec.captcha.set_warp(warp)
+    }
+
+    fn set_noise(&mut self, noise: Noise) {
+        self.spec.captcha.set_noise(noise)
+    }
+
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.spec.captcha.set_rng(rng)
+    }
 }
 
-// #[cfg(test)]
-// mod test {
-//     use super::*;
-//     use std::fs::File;
-//
-//     #[test]
-//     fn it_works() {
-//         let mut file = File::create("test2.png").unwrap();
-//         let mut captcha = ArithmeticCaptcha::new();
-//         captcha.out(&mut file);
-//         println!("{}", captcha.spec.captcha.chars.unwrap())
-//     }
-// }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn it_renders_identically_with_same_seed() {
+        let render = |seed| {
+            let mut captcha = ArithmeticCaptcha::with_seed(seed);
+            let mut data = Vec::new();
+            captcha.out(&mut data).unwrap();
+            (
+                captcha.get_arithmetic_string().unwrap(),
+                captcha.get_chars().unwrap(),
+                data,
+            )
+        };
+
+        let (problem, answer, _) = render(42);
+        assert_eq!(problem, "5-2=?");
+        assert_eq!(answer, vec!['3']);
+        // 渲染结果的快照，绘制逻辑有意变化时需要更新
+        let digest = format!("{:x}", Sha256::digest(render(42).2));
+        assert_eq!(
+            digest,
+            "ea22a8e8d15dbe51e1be33eea82fd60384bf3f63158beed9e936be4ed71cd1ea"
+        );
+        assert_eq!(render(42), render(42));
+        assert_ne!(render(42).2, render(43).2);
+    }
+
+    #[test]
+    fn it_draws_division_with_fonts_lacking_the_sign() {
+        let mut divisions = 0;
+        for seed in 0..40 {
+            let mut captcha = ArithmeticCaptcha::with_seed(seed);
+            captcha.support_algorithm_sign(5);
+            captcha.out(Vec::new()).unwrap();
+            if captcha.get_arithmetic_string().unwrap().contains('÷') {
+                divisions += 1;
+            }
+        }
+        assert!(divisions > 0);
+    }
+
+    #[test]
+    fn it_rejects_problems_without_numbers() {
+        let mut captcha = ArithmeticCaptcha::with_size_and_len(130, 48, 0);
+        assert!(matches!(captcha.get_chars(), Err(Error::InvalidConfig(_))));
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+//! Arithmetic GIF Captcha
+//!
+//! GIF格式算术动态验证码
+//!
+
+use crate::base::captcha::AbstractCaptcha;
+
+use crate::captcha::arithmetic::Arithmetic;
+use crate::captcha::gif::GifCaptcha;
+use crate::{
+    Background, CaptchaFont, CaptchaType, Charset, Error, FontSource, GlyphLayout, GlyphTransform,
+    NewCaptcha, Noise, Warp,
+};
+
+use rand::RngCore;
+use std::io::Write;
+
+/// 算术动态验证码
+///
+/// 算式的每个字符会像[GifCaptcha]一样逐帧渐隐渐现，[get_chars](AbstractCaptcha::get_chars)返回的仍是算式的计算结果。
+///
+/// Every character of the arithmetic problem fades in and out frame by frame like [GifCaptcha], while
+/// [get_chars](AbstractCaptcha::get_chars) still returns the result of the problem.
+pub struct ArithmeticGifCaptcha {
+    pub(crate) gif: GifCaptcha,
+
+    /// 算式生成器
+    arithmetic: Arithmetic,
+}
+
+impl ArithmeticGifCaptcha {
+    /// 生成一个算式
+    ///
+    /// Generate a new arithmetic problem.
+    pub fn alphas(&mut self) -> Result<Vec<char>, Error> {
+        self.arithmetic.alphas(&mut self.gif.captcha)
+    }
+
+    /// 获取算式的字符串
+    ///
+    /// Get the String of the arithmetic problem
+    pub fn get_arithmetic_string(&mut self) -> Result<String, Error> {
+        self.arithmetic.get_arithmetic_string(&mut self.gif.captcha)
+    }
+
+    /// 设置算术的难度；默认值为10
+    ///
+    /// Set the difficulty of the arithmetic problem; default as 10.
+    pub fn set_difficulty(&mut self, difficulty: usize) {
+        self.arithmetic.set_difficulty(difficulty)
+    }
+
+    /// 设置包含的算术符号。可选2~5，难度依次由低到高（加-减-乘-除）
+    ///
+    /// Set the arithmetic symbol used in the arithmetic problem; Available from 2 to 5, which includes Plus, Minus,
+    /// Multiply and Divide.
+    pub fn support_algorithm_sign(&mut self, algorithm_sign: usize) {
+        self.arithmetic.support_algorithm_sign(algorithm_sign)
+    }
+}
+
+impl NewCaptcha for ArithmeticGifCaptcha {
+    fn new() -> Self {
+        let mut gif = GifCaptcha::new();
+        gif.captcha.len = 2;
+
+        Self {
+            gif,
+            arithmetic: Arithmetic::new(),
+        }
+    }
+
+    fn with_size(width: i32, height: i32) -> Self {
+        let mut sf = Self::new();
+        sf.gif.captcha.width = width;
+        sf.gif.captcha.height = height;
+        sf
+    }
+
+    fn with_size_and_len(width: i32, height: i32, len: usize) -> Self {
+        let mut sf = Self::new();
+        sf.gif.captcha.width = width;
+        sf.gif.captcha.height = height;
+        sf.gif.captcha.len = len;
+        sf
+    }
+
+    fn with_all(width: i32, height: i32, len: usize, font: CaptchaFont, font_size: f32) -> Self {
+        let mut sf = Self::with_size_and_len(width, height, len);
+        sf.gif.captcha.set_font_by_enum(font, Some(font_size));
+        sf
+    }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        let mut sf = Self::new();
+        sf.gif.captcha.set_rng(rng);
+        sf
+    }
+}
+
+impl AbstractCaptcha for ArithmeticGifCaptcha {
+    fn out(&mut self, out: impl Write) -> Result<(), Error> {
+        let arithmetic_string = self.arithmetic.display_chars(&mut self.gif.captcha)?;
+        self.gif.graphics_gif(&arithmetic_string, out)
+    }
+
+    fn get_chars(&mut self) -> Result<Vec<char>, Error> {
+        self.arithmetic.get_chars(&mut self.gif.captcha)
+    }
+
+    fn base64(&mut self) -> Result<String, Error> {
+        self.base64_with_head("data:image/gif;base64,")
+    }
+
+    fn get_content_type(&mut self) -> String {
+        "image/gif".into()
+    }
+
+    fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.gif.captcha.set_char_type(char_type)
+    }
+
+    fn set_charset(&mut self, charset: Charset) {
+        self.gif.captcha.set_charset(charset)
+    }
+
+    fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.gif.captcha.set_fonts(fonts)
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.gif.captcha.set_font_size(size)
+    }
+
+    fn set_background(&mut self, background: Background) {
+        self.gif.captcha.set_background(background)
+    }
+
+    fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.gif.captcha.set_glyph_transform(transform)
+    }
+
+    fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.gif.captcha.set_glyph_layout(layout)
+    }
+
+    fn set_warp(&mut self, warp: Warp) {
+        self.gif.captcha.set_warp(warp)
+    }
+
+    fn set_noise(&mut self, noise: Noise) {
+        self.gif.captcha.set_noise(noise)
+    }
+
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.gif.captcha.set_rng(rng)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_answers_the_animated_problem() {
+        let mut captcha = ArithmeticGifCaptcha::new();
+        let problem = captcha.get_arithmetic_string().unwrap();
+        let answer: String = captcha.get_chars().unwrap().iter().collect();
+
+        let expr = problem
+            .trim_end_matches("=?")
+            .replace('x', "*")
+            .replace('÷', "/");
+        assert_eq!(evalexpr::eval(&expr).unwrap().to_string(), answer);
+
+        let mut data = Vec::new();
+        captcha.out(&mut data).unwrap();
+        assert!(data.starts_with(b"GIF89a"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Chinese Character PNG Captcha
+//!
+//! PNG格式中文验证码
+//!
+
+use crate::base::captcha::AbstractCaptcha;
+
+use crate::captcha::spec::SpecCaptcha;
+use crate::{
+    Background, CaptchaFont, CaptchaType, Charset, Error, FontSource, GlyphLayout, GlyphTransform,
+    NewCaptcha, Noise, Warp,
+};
+
+use rand::RngCore;
+use std::io::Write;
+
+/// 中文验证码
+///
+/// 内置的十种字体均不包含中文字形，因此中文验证码会从系统中查找可用的中文字体（如`Noto Sans CJK SC`、`WenQuanYi Micro Hei`、
+/// `Microsoft YaHei`、`PingFang SC`等），您也可以通过[set_font](AbstractCaptcha::set_font)指定中文字体。若字体中没有中文字形，
+/// 输出验证码时会返回错误。
+///
+/// None of the built-in fonts contain CJK glyphs, so the Chinese Captcha looks up a CJK-capable font from the system
+/// (like `Noto Sans CJK SC`, `WenQuanYi Micro Hei`, `Microsoft YaHei`, `PingFang SC`, etc.), or you may specify one by
+/// [set_font](AbstractCaptcha::set_font). If the font has no CJK glyphs, an error will be returned when writing the
+/// Captcha.
+pub struct ChineseCaptcha {
+    pub(crate) spec: SpecCaptcha,
+}
+
+impl ChineseCaptcha {
+    /// 生成随机汉字
+    ///
+    /// Generate random Chinese characters.
+    pub fn alphas(&mut self) -> Vec<char> {
+        self.spec.captcha.alphas_han()
+    }
+}
+
+impl NewCaptcha for ChineseCaptcha {
+    fn new() -> Self {
+        let mut spec = SpecCaptcha::new();
+        spec.captcha.len = 4;
+
+        spec.captcha.set_cjk_font(Some(28.));
+
+        Self { spec }
+    }
+
+    fn with_size(width: i32, height: i32) -> Self {
+        let mut sf = Self::new();
+        sf.spec.captcha.width = width;
+        sf.spec.captcha.height = height;
+        sf
+    }
+
+    fn with_size_and_len(width: i32, height: i32, len: usize) -> Self {
+        let mut sf = Self::new();
+        sf.spec.captcha.width = width;
+        sf.spec.captcha.height = height;
+        sf.spec.captcha.len = len;
+        sf
+    }
+
+    /// 内置字体不包含中文字形，`font`参数会被忽略，仅使用`font_size`。
+    ///
+    /// The built-in fonts contain no CJK glyphs, so `font` is ignored and only `font_size` is used.
+    fn with_all(width: i32, height: i32, len: usize, _font: CaptchaFont, font_size: f32) -> Self {
+        let mut sf = Self::with_size_and_len(width, height, len);
+        sf.spec.captcha.set_cjk_font(Some(font_size));
+        sf
+    }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        let mut sf = Self::new();
+        sf.spec.captcha.set_rng(rng);
+        sf
+    }
+}
+
+impl AbstractCaptcha for ChineseCaptcha {
+    fn out(&mut self, out: impl Write) -> Result<(), Error> {
+        // 字体中没有中文字形时，绘制时会返回错误
+        let chars = self.get_chars()?;
+        self.spec.graphics_image(&chars, out)
+    }
+
+    fn get_chars(&mut self) -> Result<Vec<char>, Error> {
+        if self.spec.captcha.chars.is_none() {
+            self.alphas();
+        }
+        Ok(self.spec.captcha.text_char())
+    }
+
+    fn base64(&mut self) -> Result<String, Error> {
+        self.base64_with_head("data:image/png;base64,")
+    }
+
+    fn get_content_type(&mut self) -> String {
+        "image/png".into()
+    }
+
+    fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.spec.captcha.set_char_type(char_type)
+    }
+
+    fn set_charset(&mut self, charset: Charset) {
+        self.spec.captcha.set_charset(charset)
+    }
+
+    fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.spec.captcha.set_fonts(fonts)
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.spec.captcha.set_font_size(size)
+    }
+
+    fn set_background(&mut self, background: Background) {
+        self.spec.captcha.set_background(background)
+    }
+
+    fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.spec.captcha.set_glyph_transform(transform)
+    }
+
+    fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.spec.captcha.set_glyph_layout(layout)
+    }
+
+    fn set_warp(&mut self, warp: Warp) {
+        self.spec.captcha.set_warp(warp)
+    }
+
+    fn set_noise(&mut self, noise: Noise) {
+        self.spec.captcha.set_noise(noise)
+    }
+
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.spec.captcha.set_rng(rng)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::base::randoms::HAN;
+    use crate::utils::test_font;
+    use crate::FontError;
+
+    #[test]
+    fn it_generates_common_chinese_characters() {
+        let mut captcha = ChineseCaptcha::new();
+        let chars = captcha.get_chars().unwrap();
+        assert_eq!(chars.len(), 4);
+        assert!(chars.iter().all(|c| HAN.contains(*c)));
+        assert_eq!(chars, captcha.get_chars().unwrap());
+    }
+
+    #[test]
+    fn it_renders_with_a_chinese_font() {
+        let mut captcha = ChineseCaptcha::new();
+        captcha.set_font(FontSource::from_bytes(test_font::han_font()).unwrap());
+        let mut data = Vec::new();
+        captcha.out(&mut data).unwrap();
+        assert!(data.starts_with(b"\x89PNG"));
+        assert!(captcha
+            .get_chars()
+            .unwrap()
+            .iter()
+            .all(|c| HAN.contains(*c)));
+    }
+
+    #[test]
+    fn it_reports_fonts_without_chinese_glyphs() {
+        let mut captcha = ChineseCaptcha::new();
+        captcha.set_font(CaptchaFont::Font1);
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::Font(FontError::MissingGlyph(c))) if HAN.contains(c)
+        ));
+    }
+}
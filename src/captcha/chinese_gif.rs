@@ -0,0 +1,172 @@
+//! Chinese Character GIF Captcha
+//!
+//! GIF格式中文动态验证码
+//!
+
+use crate::base::captcha::AbstractCaptcha;
+
+use crate::captcha::gif::GifCaptcha;
+use crate::{
+    Background, CaptchaFont, CaptchaType, Charset, Error, FontSource, GlyphLayout, GlyphTransform,
+    NewCaptcha, Noise, Warp,
+};
+
+use rand::RngCore;
+use std::io::Write;
+
+/// 中文动态验证码
+///
+/// 与[ChineseCaptcha](crate::captcha::chinese::ChineseCaptcha)相同，需要系统中安装有中文字体。
+///
+/// Same as [ChineseCaptcha](crate::captcha::chinese::ChineseCaptcha), a CJK-capable font is required in the system.
+pub struct ChineseGifCaptcha {
+    pub(crate) gif: GifCaptcha,
+}
+
+impl ChineseGifCaptcha {
+    /// 生成随机汉字
+    ///
+    /// Generate random Chinese characters.
+    pub fn alphas(&mut self) -> Vec<char> {
+        self.gif.captcha.alphas_han()
+    }
+}
+
+impl NewCaptcha for ChineseGifCaptcha {
+    fn new() -> Self {
+        let mut gif = GifCaptcha::new();
+        gif.captcha.len = 4;
+        gif.captcha.set_cjk_font(Some(28.));
+
+        Self { gif }
+    }
+
+    fn with_size(width: i32, height: i32) -> Self {
+        let mut sf = Self::new();
+        sf.gif.captcha.width = width;
+        sf.gif.captcha.height = height;
+        sf
+    }
+
+    fn with_size_and_len(width: i32, height: i32, len: usize) -> Self {
+        let mut sf = Self::new();
+        sf.gif.captcha.width = width;
+        sf.gif.captcha.height = height;
+        sf.gif.captcha.len = len;
+        sf
+    }
+
+    /// 内置字体不包含中文字形，`font`参数会被忽略，仅使用`font_size`。
+    ///
+    /// The built-in fonts contain no CJK glyphs, so `font` is ignored and only `font_size` is used.
+    fn with_all(width: i32, height: i32, len: usize, _font: CaptchaFont, font_size: f32) -> Self {
+        let mut sf = Self::with_size_and_len(width, height, len);
+        sf.gif.captcha.set_cjk_font(Some(font_size));
+        sf
+    }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        let mut sf = Self::new();
+        sf.gif.captcha.set_rng(rng);
+        sf
+    }
+}
+
+impl AbstractCaptcha for ChineseGifCaptcha {
+    fn out(&mut self, out: impl Write) -> Result<(), Error> {
+        // 字体中没有中文字形时，绘制时会返回错误
+        let chars = self.get_chars()?;
+        self.gif.graphics_gif(&chars, out)
+    }
+
+    fn get_chars(&mut self) -> Result<Vec<char>, Error> {
+        if self.gif.captcha.chars.is_none() {
+            self.alphas();
+        }
+        Ok(self.gif.captcha.text_char())
+    }
+
+    fn base64(&mut self) -> Result<String, Error> {
+        self.base64_with_head("data:image/gif;base64,")
+    }
+
+    fn get_content_type(&mut self) -> String {
+        "image/gif".into()
+    }
+
+    fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.gif.captcha.set_char_type(char_type)
+    }
+
+    fn set_charset(&mut self, charset: Charset) {
+        self.gif.captcha.set_charset(charset)
+    }
+
+    fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.gif.captcha.set_fonts(fonts)
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.gif.captcha.set_font_size(size)
+    }
+
+    fn set_background(&mut self, background: Background) {
+        self.gif.captcha.set_background(background)
+    }
+
+    fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.gif.captcha.set_glyph_transform(transform)
+    }
+
+    fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.gif.captcha.set_glyph_layout(layout)
+    }
+
+    fn set_warp(&mut self, warp: Warp) {
+        self.gif.captcha.set_warp(warp)
+    }
+
+    fn set_noise(&mut self, noise: Noise) {
+        self.gif.captcha.set_noise(noise)
+    }
+
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.gif.captcha.set_rng(rng)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::base::randoms::HAN;
+    use crate::utils::test_font;
+    use crate::FontError;
+
+    #[test]
+    fn it_renders_one_frame_per_character() {
+        let mut captcha = ChineseGifCaptcha::new();
+        captcha.set_font(FontSource::from_bytes(test_font::han_font()).unwrap());
+        let mut data = Vec::new();
+        captcha.out(&mut data).unwrap();
+
+        let chars = captcha.get_chars().unwrap();
+        assert!(chars.iter().all(|c| HAN.contains(*c)));
+
+        let mut decoder = gif::DecodeOptions::new().read_info(&data[..]).unwrap();
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, chars.len());
+    }
+
+    #[test]
+    fn it_reports_fonts_without_chinese_glyphs() {
+        let mut captcha = ChineseGifCaptcha::new();
+        captcha.set_font(CaptchaFont::Font1);
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::Font(FontError::MissingGlyph(c))) if HAN.contains(c)
+        ));
+    }
+}
@@ -3,15 +3,20 @@
 //! GIF动态验证码
 //!
 
+use crate::base::background::BackgroundParams;
 use crate::base::captcha::{AbstractCaptcha, Captcha};
+use crate::base::layout::GlyphPlacement;
+use crate::base::warp::WarpParams;
 
 use crate::utils::color::Color;
-use crate::{CaptchaFont, NewCaptcha};
-use font_kit::canvas::RasterizationOptions;
-
-use font_kit::hinting::HintingOptions;
+use crate::utils::png::rgba_data;
+use crate::{
+    Background, CaptchaFont, CaptchaType, Charset, Error, FontSource, GlyphLayout, GlyphTransform,
+    NewCaptcha, Noise, Warp,
+};
 use gif::Repeat;
-use raqote::{BlendMode, DrawOptions, DrawTarget, Point, SolidSource, Source, StrokeStyle};
+use rand::RngCore;
+use raqote::{BlendMode, DrawOptions, DrawTarget, SolidSource, Source, StrokeStyle, Transform};
 use std::io::Write;
 
 /// GIF动态验证码
@@ -26,49 +31,39 @@ impl GifCaptcha {
     ///
     /// fontColor 随机字体颜色
     // 	strs 字符数组
+    // 	placements 每个字符的位置和变换，各帧保持一致
+    // 	background 背景，各帧保持一致
+    // 	warp 图像的扭曲，各帧保持一致
     // 	flag 透明度
     // 	besselXY 干扰线参数
     pub(crate) fn graphics_image(
         &mut self,
-        color: &Vec<Color>,
-        str: &Vec<char>,
+        color: &[Color],
+        str: &[char],
+        placements: &[GlyphPlacement],
+        background: &BackgroundParams,
+        warp: Option<&WarpParams>,
         flag: usize,
         // bessel_xy: [[f32; 2]; 3],
-    ) -> ImageBuffer {
+    ) -> Result<ImageBuffer, Error> {
         let width = self.captcha.width;
         let height = self.captcha.height;
 
         let mut dt = DrawTarget::new(width, height);
-        let ref mut randoms = self.captcha.randoms;
+        let randoms = &mut self.captcha.randoms;
 
         // 填充背景
-        dt.fill_rect(
-            0.,
-            0.,
-            width as f32,
-            height as f32,
-            &Source::Solid(SolidSource::from(raqote::Color::new(255, 255, 255, 255))),
-            &DrawOptions::new(),
-        );
+        background.apply(&mut dt);
 
-        // 画干扰圆
-        let alpha = 0.1 * randoms.num(10) as f32;
-        self.captcha.draw_oval_with_option(
-            2,
+        // 画干扰元素，干扰圆使用随机的透明度
+        let alpha = 0.1 * randoms.num(10)? as f32;
+        self.captcha.draw_noise(
             &mut dt,
-            None,
             DrawOptions {
                 blend_mode: BlendMode::SrcOver,
                 alpha,
                 ..Default::default()
             },
-        );
-
-        // 画干扰线
-        self.captcha.draw_bessel_line_with_all_option(
-            1,
-            &mut dt,
-            None,
             StrokeStyle {
                 width: 1.2,
                 ..Default::default()
@@ -78,72 +73,90 @@ impl GifCaptcha {
                 alpha: 0.7,
                 ..Default::default()
             },
-        );
+        )?;
 
         // 画验证码
-        let font = self.captcha.get_font();
         let font_size = self.captcha.get_font_size();
-        let glyph = font.glyph_for_char('W').unwrap();
-        let bounds = font
-            .raster_bounds(
-                glyph,
-                font_size,
-                Default::default(),
-                HintingOptions::None,
-                RasterizationOptions::GrayscaleAa,
-            )
-            .unwrap();
-
         let mut text_draw_options = DrawOptions {
             blend_mode: BlendMode::SrcOver,
             ..Default::default()
         };
 
-        let f_w = width / str.len() as i32; // 每个字符所占宽度
-        let f_sp = (f_w - bounds.width()) / 2; // 字符的左右边距
-        for (i, ch) in str.iter().enumerate() {
+        for (i, placement) in placements.iter().enumerate() {
             let mut color = color[i].clone();
-            let alpha = self.get_alpha(flag, i);
+            let alpha = Self::get_alpha(str.len(), flag, i);
             color.set_alpha(alpha as f64);
 
             let color: raqote::Color = color.into();
-            let glyph = font.glyph_for_char(ch.clone());
-            if glyph.is_none() {
-                continue;
-            }
-
-            let bounds = font
-                .raster_bounds(
-                    glyph.unwrap(),
-                    font_size,
-                    Default::default(),
-                    HintingOptions::None,
-                    RasterizationOptions::GrayscaleAa,
-                )
-                .unwrap();
-
-            let f_y = height - ((height - bounds.height() as i32) >> 1);
             text_draw_options.alpha = alpha;
 
+            dt.set_transform(&placement.transform);
             dt.draw_glyphs(
-                &font,
+                &placement.font,
                 font_size,
-                &[glyph.unwrap()],
-                &[Point::new(
-                    (i as i32 * f_w + f_sp + 3) as f32,
-                    f_y as f32 - 3.,
-                )],
+                &[placement.glyph],
+                &[placement.origin],
                 &Source::Solid(SolidSource::from(color)),
                 &text_draw_options,
-            )
+            );
+            dt.set_transform(&Transform::identity());
         }
 
-        Vec::from(dt.get_data_u8())
+        // 扭曲图像
+        if let Some(warp) = warp {
+            warp.apply(&mut dt);
+        }
+
+        // 画椒盐噪点
+        self.captcha.draw_speckle(&mut dt)?;
+
+        Ok(rgba_data(&dt))
+    }
+
+    /// 画出GIF的每一帧并写入输出
+    pub(crate) fn graphics_gif(&mut self, str: &[char], out: impl Write) -> Result<(), Error> {
+        self.captcha.check_config(str.len())?;
+        let (width, height) = match (
+            u16::try_from(self.captcha.width),
+            u16::try_from(self.captcha.height),
+        ) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => {
+                return Err(Error::InvalidConfig(format!(
+                    "The size {}x{} is too large for a GIF image.",
+                    self.captcha.width, self.captcha.height
+                )))
+            }
+        };
+
+        // 随机生成每个文字的颜色
+        let font_color: Vec<_> = str.iter().map(|_| self.captcha.color()).collect();
+        let placements = self.captcha.glyph_placements(str)?;
+        let background = self.captcha.background_params();
+        let warp = self.captcha.warp_params();
+
+        // 开始画gif的每一帧
+        let mut encoder = gif::Encoder::new(out, width, height, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        for i in 0..str.len() {
+            let mut image =
+                self.graphics_image(&font_color, str, &placements, &background, warp.as_ref(), i)?;
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut image, 10);
+            frame.delay = 10;
+            // 背景透明时清除上一帧，避免各帧叠加
+            frame.dispose = gif::DisposalMethod::Background;
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
     }
 
     /// 获取透明度,从0到1,自动计算步长
-    fn get_alpha(&self, i: usize, j: usize) -> f32 {
-        let len = self.captcha.len;
+    fn get_alpha(len: usize, i: usize, j: usize) -> f32 {
+        // 只有一个字符时不做渐变
+        if len <= 1 {
+            return 1.;
+        }
 
         let num = i + j;
         let r = 1. / (len - 1) as f32;
@@ -180,49 +193,80 @@ impl NewCaptcha for GifCaptcha {
             captcha: Captcha::with_all(width, height, len, font, font_size),
         }
     }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        Self {
+            captcha: Captcha::with_rng(rng),
+        }
+    }
 }
 
 impl AbstractCaptcha for GifCaptcha {
-    type Error = gif::EncodingError;
-
-    fn out(&mut self, out: impl Write) -> Result<(), Self::Error> {
+    fn out(&mut self, out: impl Write) -> Result<(), Error> {
         let str = self.captcha.text_char();
-
-        // 随机生成每个文字的颜色
-        let font_color: Vec<_> = str.iter().map(|_| self.captcha.color()).collect();
-
-        // 开始画gif的每一帧
-        let width = self.captcha.width as u16;
-        let height = self.captcha.height as u16;
-        let mut encoder = gif::Encoder::new(out, width, height, &[])?;
-        encoder.set_repeat(Repeat::Infinite)?;
-        for i in 0..self.captcha.len {
-            let mut image = self.graphics_image(&font_color, &str, i);
-            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut image, 10);
-            frame.delay = 10;
-            encoder.write_frame(&frame)?;
-        }
-
-        Ok(())
+        self.graphics_gif(&str, out)
     }
 
-    fn get_chars(&mut self) -> Vec<char> {
-        self.captcha.text_char()
+    fn get_chars(&mut self) -> Result<Vec<char>, Error> {
+        Ok(self.captcha.text_char())
     }
 
-    fn base64(&mut self) -> Result<String, Self::Error> {
+    fn base64(&mut self) -> Result<String, Error> {
         self.base64_with_head("data:image/gif;base64,")
     }
 
     fn get_content_type(&mut self) -> String {
         "image/gif".into()
     }
+
+    fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.captcha.set_char_type(char_type)
+    }
+
+    fn set_charset(&mut self, charset: Charset) {
+        self.captcha.set_charset(charset)
+    }
+
+    fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.captcha.set_fonts(fonts)
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.captcha.set_font_size(size)
+    }
+
+    fn set_background(&mut self, background: Background) {
+        self.captcha.set_background(background)
+    }
+
+    fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.captcha.set_glyph_transform(transform)
+    }
+
+    fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.captcha.set_glyph_layout(layout)
+    }
+
+    fn set_warp(&mut self, warp: Warp) {
+        self.captcha.set_warp(warp)
+    }
+
+    fn set_noise(&mut self, noise: Noise) {
+        self.captcha.set_noise(noise)
+    }
+
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.captcha.set_rng(rng)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::captcha::arithmetic::ArithmeticCaptcha;
+    use crate::captcha::render_with;
+    use crate::utils::test_image::{self, Pixels};
+    use sha2::{Digest, Sha256};
     use std::fs::File;
 
     #[test]
@@ -232,4 +276,149 @@ mod test {
         captcha.spec.captcha.len = 3;
         captcha.out(&mut file).unwrap();
     }
+
+    #[test]
+    fn it_renders_identically_with_same_seed() {
+        let render = |seed| render_with(|_| GifCaptcha::with_seed(seed));
+
+        assert_eq!(render(42).0, "WR7BA".chars().collect::<Vec<_>>());
+        // 渲染结果的快照，绘制逻辑有意变化时需要更新
+        let digest = format!("{:x}", Sha256::digest(render(42).1));
+        assert_eq!(
+            digest,
+            "fd5a3a08d752dcada19bfd00cd4df88e940851d99180ea1cd9d99cc185e17906"
+        );
+        assert_eq!(render(42), render(42));
+        assert_ne!(render(42).1, render(43).1);
+    }
+
+    /// 测试用的纯色背景，常用颜色中每种都至少有一个通道与白色相差100以上
+    const BACKGROUND: [u8; 3] = [255, 255, 255];
+
+    /// 在纯色背景上、不画干扰元素，渲染种子为`seed`的验证码的每一帧
+    fn render_plain(seed: u64, f: impl FnOnce(GifCaptcha) -> GifCaptcha) -> Vec<Pixels> {
+        let [r, g, b] = BACKGROUND;
+        let mut captcha = f(GifCaptcha::with_seed(seed)
+            .with_background(Background::solid((r, g, b)))
+            .with_noise(Noise::none()));
+        let mut data = Vec::new();
+        captcha.out(&mut data).unwrap();
+        test_image::decode_gif(&data)
+    }
+
+    /// 所有帧中与背景明显不同的像素的横坐标范围
+    fn ink_columns(frames: &[Pixels]) -> (usize, usize) {
+        let columns = frames
+            .iter()
+            .flat_map(|frame| frame.ink(BACKGROUND, 64))
+            .map(|(x, _)| x);
+        columns.fold((usize::MAX, 0), |(min, max), x| (min.min(x), max.max(x)))
+    }
+
+    #[test]
+    fn it_keeps_transformed_glyphs_on_every_frame() {
+        let extreme = GlyphTransform::none()
+            .with_rotation(30.)
+            .with_scale(1.3, 1.3)
+            .with_shear(0.4);
+        for seed in 0..5 {
+            let frames = render_plain(seed, |c| c.with_glyph_transform(extreme));
+            assert_eq!(frames.len(), 5);
+            for frame in frames {
+                // 调色板量化会带来少许色差
+                for pixel in frame.border() {
+                    assert!((0..3).all(|c| pixel[c].abs_diff(BACKGROUND[c]) <= 8));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_condenses_glyphs_in_every_frame() {
+        let (cells_left, cells_right) = ink_columns(&render_plain(42, |c| c));
+        let (left, right) = ink_columns(&render_plain(42, |c| {
+            c.with_glyph_layout(GlyphLayout::condensed().with_overlap(0.5))
+        }));
+
+        // 重叠排列的字符占据的宽度更窄，并且居中
+        assert!(right - left < cells_right - cells_left);
+        assert!(left > cells_left && right < cells_right);
+    }
+
+    #[test]
+    fn it_warps_every_frame_within_the_amplitude() {
+        let amplitude = 4;
+        let plain = render_plain(42, |c| c);
+        let warped = render_plain(42, |c| c.with_warp(Warp::none().with_amplitude(4.)));
+        assert_eq!(plain.len(), warped.len());
+
+        let mut moved = false;
+        for (plain, warped) in plain.iter().zip(&warped) {
+            for point in warped.ink(BACKGROUND, 48) {
+                assert!(plain.has_ink_near(point, amplitude + 1, BACKGROUND, 24));
+                moved |= !plain.is_ink(point.0, point.1, BACKGROUND, 24);
+            }
+        }
+        assert!(moved);
+    }
+
+    #[test]
+    fn it_draws_the_configured_speckle_in_every_frame() {
+        // 白色的噪点与背景相同，只统计黑色的噪点，约占全部噪点的一半
+        let speckles = |frame: &Pixels| {
+            frame
+                .all()
+                .filter(|p| p[..3].iter().all(|c| *c < 16))
+                .count()
+        };
+        for frame in render_plain(42, |c| c) {
+            assert_eq!(speckles(&frame), 0);
+        }
+
+        let frames = render_plain(42, |c| c.with_noise(Noise::none().with_speckle(0.05)));
+        for frame in frames {
+            let total = frame.width * frame.height;
+            let count = speckles(&frame);
+            assert!(count <= total / 20 && count >= total / 50, "{}", count);
+        }
+    }
+
+    #[test]
+    fn it_fills_the_background_of_every_frame() {
+        let render = |background| {
+            render_with(|c: GifCaptcha| c.with_background(background).with_noise(Noise::none())).1
+        };
+        let corners = |data: Vec<u8>| {
+            let mut options = gif::DecodeOptions::new();
+            options.set_color_output(gif::ColorOutput::RGBA);
+            let mut decoder = options.read_info(&data[..]).unwrap();
+            let mut corners = Vec::new();
+            while let Some(frame) = decoder.read_next_frame().unwrap() {
+                corners.push(frame.buffer[..4].to_vec());
+            }
+            corners
+        };
+
+        let dark = corners(render(Background::solid((24, 26, 33))));
+        assert_eq!(dark.len(), 5);
+        for corner in dark {
+            assert!(corner[0].abs_diff(24) <= 8);
+            assert!(corner[1].abs_diff(26) <= 8);
+            assert!(corner[2].abs_diff(33) <= 8);
+            assert_eq!(corner[3], 255);
+        }
+
+        for corner in corners(render(Background::transparent())) {
+            assert_eq!(corner[3], 0);
+        }
+    }
+
+    #[test]
+    fn it_rejects_sizes_beyond_gif_limits() {
+        let mut captcha = GifCaptcha::with_size(70000, 48);
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
 }
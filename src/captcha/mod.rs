@@ -1,5 +1,19 @@
 //! 不同种类的Captcha实现  Different types of Captcha
 
 pub mod arithmetic;
+pub mod arithmetic_gif;
+pub mod chinese;
+pub mod chinese_gif;
 pub mod gif;
 pub mod spec;
+
+/// 以种子42创建验证码，经`f`配置后渲染，返回验证码的字符和图像数据
+#[cfg(test)]
+pub(crate) fn render_with<T: crate::AbstractCaptcha>(
+    f: impl FnOnce(T) -> T,
+) -> (Vec<char>, Vec<u8>) {
+    let mut captcha = f(T::with_seed(42));
+    let mut data = Vec::new();
+    captcha.out(&mut data).unwrap();
+    (captcha.get_chars().unwrap(), data)
+}
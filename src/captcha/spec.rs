@@ -6,15 +6,14 @@
 use crate::base::captcha::{AbstractCaptcha, Captcha};
 
 use crate::utils::png::WritePng;
-use crate::{CaptchaFont, NewCaptcha};
-use font_kit::canvas::RasterizationOptions;
-
-use font_kit::hinting::HintingOptions;
-use raqote::{DrawOptions, DrawTarget, Point, SolidSource, Source};
+use crate::{
+    Background, CaptchaFont, CaptchaType, Charset, Error, FontSource, GlyphLayout, GlyphTransform,
+    NewCaptcha, Noise, Warp,
+};
+use rand::RngCore;
+use raqote::{DrawOptions, DrawTarget, SolidSource, Source, StrokeStyle, Transform};
 use std::io::Write;
 
-
-
 /// PNG格式验证码
 pub struct SpecCaptcha {
     pub(crate) captcha: Captcha,
@@ -22,81 +21,54 @@ pub struct SpecCaptcha {
 
 impl SpecCaptcha {
     /// 生成验证码图形
-    pub(crate) fn graphics_image(
-        &mut self,
-        str: &Vec<char>,
-        out: impl Write,
-    ) -> Result<(), png::EncodingError> {
+    pub(crate) fn graphics_image(&mut self, str: &[char], out: impl Write) -> Result<(), Error> {
+        self.captcha.check_config(str.len())?;
         let width = self.captcha.width;
         let height = self.captcha.height;
 
         let mut dt = DrawTarget::new(width, height);
 
         // 填充背景
-        dt.fill_rect(
-            0.,
-            0.,
-            width as f32,
-            height as f32,
-            &Source::Solid(SolidSource::from(raqote::Color::new(255, 255, 255, 255))),
-            &DrawOptions::new(),
-        );
+        self.captcha.background_params().apply(&mut dt);
 
-        // 画干扰圆
-        self.captcha.draw_oval(2, &mut dt, None);
-
-        // 画干扰线
-        self.captcha.draw_bessel_line(1, &mut dt, None);
+        // 画干扰元素
+        self.captcha.draw_noise(
+            &mut dt,
+            DrawOptions::new(),
+            StrokeStyle {
+                width: 2.,
+                ..Default::default()
+            },
+            DrawOptions::new(),
+        )?;
 
         // 画字符串
-        let font = self.captcha.get_font();
         let font_size = self.captcha.get_font_size();
-        let glyph = font.glyph_for_char('W').unwrap();
-        let bounds = font
-            .raster_bounds(
-                glyph,
-                font_size,
-                Default::default(),
-                HintingOptions::None,
-                RasterizationOptions::GrayscaleAa,
-            )
-            .unwrap();
-
-        let f_w = width / str.len() as i32; // 每个字符所占宽度
-        let f_sp = (f_w - bounds.width()) / 2; // 字符的左右边距
-        for (i, ch) in str.iter().enumerate() {
+        let placements = self.captcha.glyph_placements(str)?;
+        for placement in &placements {
             let color: raqote::Color = self.captcha.color().into();
-            let glyph = font.glyph_for_char(ch.clone());
-            if glyph.is_none() {
-                continue;
-            }
-
-            let bounds = font
-                .raster_bounds(
-                    glyph.unwrap(),
-                    font_size,
-                    Default::default(),
-                    HintingOptions::None,
-                    RasterizationOptions::GrayscaleAa,
-                )
-                .unwrap();
-
-            let f_y = height - ((height - bounds.height() as i32) >> 1);
 
+            dt.set_transform(&placement.transform);
             dt.draw_glyphs(
-                &font,
+                &placement.font,
                 font_size,
-                &[glyph.unwrap()],
-                &[Point::new(
-                    (i as i32 * f_w + f_sp + 3) as f32,
-                    f_y as f32 - 3.,
-                )],
+                &[placement.glyph],
+                &[placement.origin],
                 &Source::Solid(SolidSource::from(color)),
                 &DrawOptions::new(),
-            )
+            );
+            dt.set_transform(&Transform::identity());
+        }
+
+        // 扭曲图像
+        if let Some(warp) = self.captcha.warp_params() {
+            warp.apply(&mut dt);
         }
 
-        dt.write_png(out)
+        // 画椒盐噪点
+        self.captcha.draw_speckle(&mut dt)?;
+
+        Ok(dt.write_png(out)?)
     }
 }
 
@@ -124,31 +96,323 @@ impl NewCaptcha for SpecCaptcha {
             captcha: Captcha::with_all(width, height, len, font, font_size),
         }
     }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        Self {
+            captcha: Captcha::with_rng(rng),
+        }
+    }
 }
 
 impl AbstractCaptcha for SpecCaptcha {
-    type Error = png::EncodingError;
-
-    fn out(&mut self, out: impl Write) -> Result<(), Self::Error> {
+    fn out(&mut self, out: impl Write) -> Result<(), Error> {
         let text_char = self.captcha.text_char();
         self.graphics_image(&text_char, out)
     }
 
-    fn get_chars(&mut self) -> Vec<char> {
-        self.captcha.text_char()
+    fn get_chars(&mut self) -> Result<Vec<char>, Error> {
+        Ok(self.captcha.text_char())
     }
 
-    fn base64(&mut self) -> Result<String, Self::Error> {
+    fn base64(&mut self) -> Result<String, Error> {
         self.base64_with_head("data:image/png;base64,")
     }
 
     fn get_content_type(&mut self) -> String {
         "image/png".into()
     }
+
+    fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.captcha.set_char_type(char_type)
+    }
+
+    fn set_charset(&mut self, charset: Charset) {
+        self.captcha.set_charset(charset)
+    }
+
+    fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.captcha.set_fonts(fonts)
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.captcha.set_font_size(size)
+    }
+
+    fn set_background(&mut self, background: Background) {
+        self.captcha.set_background(background)
+    }
+
+    fn set_glyph_transform(&mut self, transform: GlyphTransform) {
+        self.captcha.set_glyph_transform(transform)
+    }
+
+    fn set_glyph_layout(&mut self, layout: GlyphLayout) {
+        self.captcha.set_glyph_layout(layout)
+    }
+
+    fn set_warp(&mut self, warp: Warp) {
+        self.captcha.set_warp(warp)
+    }
+
+    fn set_noise(&mut self, noise: Noise) {
+        self.captcha.set_noise(noise)
+    }
+
+    fn set_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.captcha.set_rng(rng)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use super::*;
+    use crate::captcha::render_with;
+    use crate::utils::png::WritePng;
+    use crate::utils::test_font;
+    use crate::utils::test_image::{self, Pixels};
+    use crate::FontError;
+    use sha2::{Digest, Sha256};
+
     #[test]
     fn it_works() {}
+
+    #[test]
+    fn it_honours_char_type() {
+        let mut captcha = SpecCaptcha::new().with_char_type(CaptchaType::TypeOnlyNumber);
+        assert!(captcha
+            .get_chars()
+            .unwrap()
+            .iter()
+            .all(char::is_ascii_digit));
+
+        captcha.set_char_type(CaptchaType::TypeOnlyUpper);
+        assert!(captcha
+            .get_chars()
+            .unwrap()
+            .iter()
+            .all(char::is_ascii_uppercase));
+    }
+
+    #[test]
+    fn it_renders_identically_with_same_seed() {
+        let render = |seed| render_with(|_| SpecCaptcha::with_seed(seed));
+
+        assert_eq!(render(42).0, "WR7BA".chars().collect::<Vec<_>>());
+        // 渲染结果的快照，绘制逻辑有意变化时需要更新
+        let digest = format!("{:x}", Sha256::digest(render(42).1));
+        assert_eq!(
+            digest,
+            "8a56b69f5b692e2b430915a491f9313535f54cb071e3d915eecb7d69f78ca633"
+        );
+        assert_eq!(render(42), render(42));
+        assert_ne!(render(42).1, render(43).1);
+    }
+
+    /// 测试用的纯色背景
+    const BACKGROUND: [u8; 3] = [128, 128, 128];
+
+    /// 在纯色背景上、不画干扰元素，渲染种子为`seed`的验证码
+    fn render_plain(seed: u64, f: impl FnOnce(SpecCaptcha) -> SpecCaptcha) -> Pixels {
+        let [r, g, b] = BACKGROUND;
+        let mut captcha = f(SpecCaptcha::with_seed(seed)
+            .with_background(Background::solid((r, g, b)))
+            .with_noise(Noise::none()));
+        let mut data = Vec::new();
+        captcha.out(&mut data).unwrap();
+        test_image::decode_png(&data)
+    }
+
+    #[test]
+    fn it_keeps_transformed_glyphs_on_the_canvas() {
+        let extreme = GlyphTransform::none()
+            .with_rotation(30.)
+            .with_scale(1.3, 1.3)
+            .with_shear(0.4);
+        for transform in [GlyphTransform::default(), extreme] {
+            for layout in [GlyphLayout::cells(), GlyphLayout::default()] {
+                for seed in 0..10 {
+                    let image = render_plain(seed, |c| {
+                        c.with_glyph_transform(transform).with_glyph_layout(layout)
+                    });
+                    assert!(!image.ink(BACKGROUND, 64).is_empty());
+                    // 最外圈没有被字符覆盖，说明字符没有超出图像而被裁掉
+                    for pixel in image.border() {
+                        assert_eq!(pixel[..3], BACKGROUND);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_warps_pixels_within_the_amplitude() {
+        let amplitude = 4;
+        let plain = render_plain(42, |c| c);
+        let warped = render_plain(42, |c| c.with_warp(Warp::none().with_amplitude(4.)));
+
+        // 双线性插值取色的位置与目标像素相距不超过振幅，再加上插值用到的相邻像素
+        let ink = warped.ink(BACKGROUND, 96);
+        assert!(!ink.is_empty());
+        for &point in &ink {
+            assert!(plain.has_ink_near(point, amplitude + 1, BACKGROUND, 90));
+        }
+        assert!(ink
+            .iter()
+            .any(|&(x, y)| !plain.is_ink(x, y, BACKGROUND, 90)));
+    }
+
+    #[test]
+    fn it_overlaps_condensed_glyphs_by_the_ratio() {
+        let mut captcha = SpecCaptcha::with_seed(42)
+            .with_glyph_layout(GlyphLayout::condensed().with_overlap(0.3));
+        let chars = captcha.get_chars().unwrap();
+        let font_size = captcha.captcha.get_font_size();
+        let placements = captcha.captcha.glyph_placements(&chars).unwrap();
+
+        let advance = |p: &crate::base::layout::GlyphPlacement| {
+            let units_per_em = p.font.metrics().units_per_em as f32;
+            p.font.advance(p.glyph).unwrap().x() * font_size / units_per_em
+        };
+        for pair in placements.windows(2) {
+            let step = pair[1].origin.x - pair[0].origin.x;
+            assert!((step - advance(&pair[0]) * 0.7).abs() < 1e-3);
+        }
+
+        // 排列后的整体居中
+        let last = placements.last().unwrap();
+        let right = last.origin.x + advance(last);
+        assert!((placements[0].origin.x - (130. - right)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn it_draws_the_configured_speckle() {
+        let speckles = |image: &Pixels| {
+            image
+                .all()
+                .filter(|p| p[..3] == [0, 0, 0] || p[..3] == [255, 255, 255])
+                .count()
+        };
+        assert_eq!(speckles(&render_plain(42, |c| c)), 0);
+
+        // 噪点可能落在同一像素上，因此实际数量略少于设置的比例
+        let image = render_plain(42, |c| c.with_noise(Noise::none().with_speckle(0.05)));
+        let total = image.width * image.height;
+        let count = speckles(&image);
+        assert!(count <= total / 20 && count >= total / 25, "{}", count);
+    }
+
+    #[test]
+    fn it_draws_the_configured_noise() {
+        let glyphs = render_plain(42, |c| c).ink(BACKGROUND, 0).len();
+        let noise = Noise::none().with_lines(3).with_curves(2).with_dots(40);
+        let noisy = render_plain(42, |c| c.with_noise(noise));
+        assert!(noisy.ink(BACKGROUND, 0).len() > glyphs + 200);
+    }
+
+    #[test]
+    fn it_fills_the_configured_background() {
+        let render = |background| {
+            let (_, data) = render_with(|c: SpecCaptcha| {
+                c.with_background(background).with_noise(Noise::none())
+            });
+            test_image::decode_png(&data)
+        };
+
+        assert_eq!(
+            render(Background::solid((24, 26, 33))).at(0, 0),
+            [24, 26, 33, 255]
+        );
+        assert_eq!(render(Background::transparent()).at(0, 0)[3], 0);
+
+        // 与图像大小相同的背景图片原样出现在验证码中
+        let mut source = DrawTarget::new(130, 48);
+        source.clear(SolidSource::from_unpremultiplied_argb(255, 200, 0, 0));
+        source.fill_rect(
+            65.,
+            0.,
+            65.,
+            48.,
+            &Source::Solid(SolidSource::from_unpremultiplied_argb(255, 0, 0, 200)),
+            &DrawOptions::new(),
+        );
+        let mut png = Vec::new();
+        source.write_png(&mut png).unwrap();
+        let image = render(Background::image(&png).unwrap());
+        assert_eq!(image.at(0, 0), [200, 0, 0, 255]);
+        assert_eq!(image.at(129, 47), [0, 0, 200, 255]);
+    }
+
+    #[test]
+    fn it_fails_on_tiny_canvas_instead_of_panicking() {
+        let mut captcha = SpecCaptcha::with_size(20, 10);
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        let mut captcha = SpecCaptcha::with_size_and_len(130, 48, 0);
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn it_fails_on_short_canvas_instead_of_panicking() {
+        // 字符比图像高时无法放下
+        for (width, height) in [(130, 20), (30, 16)] {
+            let mut captcha = SpecCaptcha::with_size(width, height);
+            assert!(matches!(
+                captcha.out(Vec::new()),
+                Err(Error::InvalidConfig(_))
+            ));
+
+            let mut captcha = crate::captcha::gif::GifCaptcha::with_size(width, height);
+            assert!(matches!(
+                captcha.out(Vec::new()),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn it_only_needs_the_sample_char_for_cells() {
+        let mut captcha = SpecCaptcha::new().with_glyph_layout(GlyphLayout::cells());
+        captcha.captcha.sample_char = Some('Ж');
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::Font(FontError::MissingGlyph('Ж')))
+        ));
+
+        // 紧凑排列按每个字形的步进宽度排列，不使用样本字符
+        let mut captcha = SpecCaptcha::new().with_glyph_layout(GlyphLayout::condensed());
+        captcha.captcha.sample_char = Some('Ж');
+        captcha.out(Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn it_samples_from_custom_charset() {
+        let charset = Charset::new("ACEGK".chars()).unwrap();
+        let mut captcha = SpecCaptcha::new().with_charset(charset.clone());
+        captcha.out(Vec::new()).unwrap();
+        assert!(captcha
+            .get_chars()
+            .unwrap()
+            .iter()
+            .all(|c| charset.chars().contains(c)));
+
+        // 内置字体没有西里尔字母的字形
+        let charset = Charset::new("АБВГД".chars()).unwrap();
+        let mut captcha = SpecCaptcha::new().with_charset(charset.clone());
+        assert!(matches!(
+            captcha.out(Vec::new()),
+            Err(Error::Font(FontError::MissingGlyph(_)))
+        ));
+
+        // 只包含西里尔字母的字体，样本字符取自字符集而不是'W'
+        let font = FontSource::from_bytes(test_font::font_for("АБВГД")).unwrap();
+        let mut captcha = SpecCaptcha::new().with_charset(charset);
+        captcha.set_font(font);
+        captcha.out(Vec::new()).unwrap();
+    }
 }
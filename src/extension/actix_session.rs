@@ -0,0 +1,166 @@
+//! Actix Web & Actix Session 组合
+//!
+//! - Actix Web: [actix-web](https://docs.rs/actix-web)
+//! - Actix Session: [actix-session](https://docs.rs/actix-session)
+//!
+//! 验证时答案会从Session中取出，无论验证码是否正确，每个答案只能验证一次。使用`CookieSessionStore`时Session保存在客户端，
+//! 客户端可以重新提交取出答案前的旧Cookie，因此每个答案都带有一个随机数，验证时通过[ReplayGuard]记录已使用过的随机数。
+//! [ver](CaptchaActixSessionStaticExt::ver)使用进程内的[MemoryReplayGuard](crate::token::MemoryReplayGuard)，
+//! 多实例部署时请使用[ver_with_guard](CaptchaActixSessionStaticExt::ver_with_guard)并提供共享存储的防重放检查。
+//!
+//! The answer is taken out of the session on verification, so every answer can be verified only once, whether the
+//! code is correct or not. With `CookieSessionStore` the session lives on the client, which may resend the old cookie
+//! from before the answer was taken. Hence every answer carries a nonce, and the used nonces are recorded through a
+//! [ReplayGuard] on verification. [ver](CaptchaActixSessionStaticExt::ver) uses an in-process
+//! [MemoryReplayGuard](crate::token::MemoryReplayGuard); for multi-instance deployments, use
+//! [ver_with_guard](CaptchaActixSessionStaticExt::ver_with_guard) with a replay guard backed by a shared storage.
+
+use crate::extension::sealed::{SealedAnswer, DEFAULT_GUARD};
+use crate::extension::{CaptchaUtil, CAPTCHA_KEY};
+use crate::token::ReplayGuard;
+use crate::{AbstractCaptcha, Error};
+use actix_session::Session;
+use actix_web::{HttpResponse, ResponseError};
+use async_trait::async_trait;
+
+/// Actix Web & Actix Session
+pub trait CaptchaActixSessionExt {
+    /// 将验证码图片写入响应，并将用户的验证码信息保存至Session中
+    ///
+    /// Write the Captcha Image into the response and save the Captcha information into the user's Session.
+    fn out(&mut self, session: &Session) -> Result<HttpResponse, Error>;
+}
+
+/// Actix Web & Actix Session - 静态方法
+#[async_trait(?Send)]
+pub trait CaptchaActixSessionStaticExt {
+    /// 验证验证码（不区分大小写），返回的布尔值代表验证码是否正确。无论是否正确，答案都会从Session中取出，并使用进程内的防重放检查。
+    ///
+    /// Verify the Captcha code, ignoring ASCII case, and return whether user's code is correct. The answer is taken
+    /// out of the session whether the code is correct or not, and an in-process replay guard is used.
+    async fn ver(code: &str, session: &Session) -> bool {
+        Self::ver_with_guard(code, session, &*DEFAULT_GUARD).await
+    }
+
+    /// 验证验证码，并使用给定的防重放检查记录已使用过的答案，参见[ver](Self::ver)
+    ///
+    /// Verify the Captcha code, recording the used answers with the given replay guard; see [ver](Self::ver).
+    async fn ver_with_guard(code: &str, session: &Session, guard: &dyn ReplayGuard) -> bool {
+        let sealed = match session.remove_as::<String>(CAPTCHA_KEY) {
+            Some(Ok(value)) => SealedAnswer::parse(&value),
+            _ => None,
+        };
+        match sealed {
+            Some(sealed) => match sealed.open(guard).await {
+                Some(ans) => ans.eq_ignore_ascii_case(code),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// 清除Session中的验证码
+    ///
+    /// Clear the Captcha in the session.
+    fn clear(session: &Session) {
+        session.remove(CAPTCHA_KEY);
+    }
+}
+
+impl<T: AbstractCaptcha> CaptchaActixSessionExt for CaptchaUtil<T> {
+    fn out(&mut self, session: &Session) -> Result<HttpResponse, Error> {
+        let mut data = vec![];
+        self.captcha_instance.out(&mut data)?;
+
+        let ans: String = self.captcha_instance.get_chars()?.iter().collect();
+        session
+            .insert(CAPTCHA_KEY, SealedAnswer::new(ans).to_string())
+            .map_err(|e| Error::Storage(Box::new(e)))?;
+
+        Ok(HttpResponse::Ok()
+            .content_type(self.captcha_instance.get_content_type())
+            .body(data))
+    }
+}
+
+impl CaptchaActixSessionStaticExt for CaptchaUtil {}
+
+/// 验证码的错误会以`500 Internal Server Error`响应，处理函数中可以直接使用`?`
+///
+/// Captcha errors respond with `500 Internal Server Error`, so `?` can be used in handlers directly.
+impl ResponseError for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::gif::GifCaptcha;
+    use crate::NewCaptcha;
+    use actix_session::storage::CookieSessionStore;
+    use actix_session::SessionMiddleware;
+    use actix_web::cookie::Key;
+    use actix_web::http::header;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn get_captcha(session: Session) -> Result<HttpResponse, Error> {
+        let mut captcha: CaptchaUtil<GifCaptcha> = CaptchaUtil::with_seed(42);
+        captcha.out(&session)
+    }
+
+    async fn verify_captcha(session: Session, code: web::Path<String>) -> HttpResponse {
+        if CaptchaUtil::ver(&code, &session).await {
+            HttpResponse::Ok().finish()
+        } else {
+            HttpResponse::Forbidden().finish()
+        }
+    }
+
+    #[actix_web::test]
+    async fn it_verifies_codes_in_the_session() {
+        let app = test::init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(
+                    CookieSessionStore::default(),
+                    Key::generate(),
+                ))
+                .route("/captcha", web::get().to(get_captcha))
+                .route("/verify/{code}", web::get().to(verify_captcha)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/captcha").to_request()).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/gif"
+        );
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+
+        let verify = |code: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/verify/{}", code))
+                .cookie(cookie.clone())
+                .to_request()
+        };
+        let resp = test::call_service(&app, verify("wr7ba")).await;
+        assert_eq!(resp.status(), 200);
+
+        // 重新提交的旧Cookie会被拒绝
+        let resp = test::call_service(&app, verify("wr7ba")).await;
+        assert_eq!(resp.status(), 403);
+
+        // 错误的验证码同样会用掉答案
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/captcha").to_request()).await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        let verify = |code: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/verify/{}", code))
+                .cookie(cookie.clone())
+                .to_request()
+        };
+        let resp = test::call_service(&app, verify("wrong")).await;
+        assert_eq!(resp.status(), 403);
+        let resp = test::call_service(&app, verify("wr7ba")).await;
+        assert_eq!(resp.status(), 403);
+    }
+}
@@ -3,26 +3,111 @@
 //! - Axum: [axum](https://docs.rs/axum)
 //! - Tower Sessions: [axum](https://docs.rs/tower-sessions)
 
-use crate::extension::CaptchaUtil;
-use crate::AbstractCaptcha;
+use crate::extension::{CaptchaUtil, CAPTCHA_KEY};
+use crate::store::{self, CaptchaJson, IssuedCaptcha};
+use crate::{AbstractCaptcha, Error, NewCaptcha};
 use async_trait::async_trait;
-use axum::response::Response;
-use log::warn;
-use std::fmt::Debug;
+use axum::body::{Body, Bytes};
+use axum::extract::{Form, FromRequest, Json, Query, Request};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use log::{error, warn};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tower_sessions::Session;
 
-const CAPTCHA_KEY: &'static str = "ez-captcha";
+/// 以JSON签发的验证码在Session中的键，值为验证码编号到答案和过期时间（Unix时间戳）的映射
+const CAPTCHA_JSON_KEY: &str = "ez-captcha-json";
+
+type OutstandingCaptchas = HashMap<String, (String, u64)>;
+
+/// 每个Session中最多保存的未验证JSON验证码数量，超出时最早过期的验证码会被丢弃
+const MAX_OUTSTANDING: usize = 16;
+
+/// 指定命名空间的验证码在Session中的键
+pub(crate) fn namespaced_key(namespace: &str) -> String {
+    format!("{}:{}", CAPTCHA_KEY, namespace)
+}
+
+async fn out_to_key<T: AbstractCaptcha>(
+    captcha: &mut T,
+    key: &str,
+    session: &Session,
+) -> Result<Response, Error> {
+    let mut data = vec![];
+    captcha.out(&mut data)?;
+
+    let ans: String = captcha.get_chars()?.iter().collect();
+    session
+        .insert(key, ans)
+        .await
+        .map_err(|e| Error::Storage(Box::new(e)))?;
+
+    let content_type = captcha.get_content_type();
+    Ok(([(header::CONTENT_TYPE, content_type)], data).into_response())
+}
+
+async fn ver_key(key: &str, code: &str, session: &Session) -> bool {
+    match session.get::<String>(key).await {
+        Ok(Some(ans)) => ans.eq_ignore_ascii_case(code),
+        _ => false,
+    }
+}
+
+async fn clear_key(key: &str, session: &Session) {
+    if session.remove::<String>(key).await.is_err() {
+        warn!("Exception occurs during clearing the session.")
+    }
+}
+
+/// 从Session中删除答案再进行比较，无论是否正确，答案都会失效
+///
+/// tower-sessions为每个请求单独加载Session，同一Session的两个并发请求可能在任何一方保存之前都读到答案，
+/// 因此这里只保证先后提交的请求不能重复使用同一个答案。
+async fn take_key(key: &str, code: &str, session: &Session) -> bool {
+    match session.remove::<String>(key).await {
+        Ok(Some(ans)) => ans.eq_ignore_ascii_case(code),
+        Ok(None) => false,
+        Err(_) => {
+            warn!("Exception occurs during clearing the session.");
+            false
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
 
 /// Axum & Tower_Sessions
 #[async_trait]
 pub trait CaptchaAxumTowerSessionExt {
-    /// 错误类型
-    type Error: Debug + Send + Sync + 'static;
-
     /// 将验证码图片写入响应，并将用户的验证码信息保存至Session中
     ///
     /// Write the Captcha Image into the response and save the Captcha information into the user's Session.
-    async fn out(&mut self, session: &Session) -> Result<Response, Self::Error>;
+    async fn out(&mut self, session: &Session) -> Result<Response, Error>;
+
+    /// 将验证码图片写入响应，并将用户的验证码信息保存至Session中指定的命名空间（例如`"login"`、`"comment"`），
+    /// 不同命名空间的验证码可以共存于同一Session中，互不覆盖。
+    ///
+    /// Write the Captcha Image into the response and save the Captcha information into the given namespace (e.g.
+    /// `"login"`, `"comment"`) of the user's Session. Captchas of different namespaces coexist in the same Session
+    /// without overwriting each other.
+    async fn out_for(&mut self, namespace: &str, session: &Session) -> Result<Response, Error>;
+
+    /// 以JSON返回验证码（参见[CaptchaJson]），答案以验证码编号为键保存至Session中，在`ttl`后过期。
+    /// 同一用户可以同时持有多个未验证的验证码，需使用[ver_by_id](CaptchaAxumTowerSessionStaticExt::ver_by_id)验证。
+    /// 每个Session最多保存16个未验证的验证码，超出时最早过期的验证码会失效。
+    ///
+    /// Return the Captcha as JSON (see [CaptchaJson]), and save the answer into the user's Session by the Captcha id,
+    /// which expires after `ttl`. A user may hold several outstanding Captchas at the same time, which should be
+    /// verified by [ver_by_id](CaptchaAxumTowerSessionStaticExt::ver_by_id). At most 16 outstanding Captchas are kept
+    /// per Session; beyond that, the one expiring first is dropped.
+    async fn out_json(&mut self, session: &Session, ttl: Duration) -> Result<Response, Error>;
 }
 
 /// Axum & Tower_Sessions - 静态方法
@@ -32,39 +117,697 @@ pub trait CaptchaAxumTowerSessionStaticExt {
     ///
     /// Verify the Captcha code, and return whether user's code is correct.
     async fn ver(code: &str, session: &Session) -> bool {
-        match session.get::<String>(CAPTCHA_KEY).await {
-            Ok(Some(ans)) => ans.to_ascii_lowercase() == code.to_ascii_lowercase(),
-            _ => false,
-        }
+        ver_key(CAPTCHA_KEY, code, session).await
     }
 
     /// 清除Session中的验证码
     ///
     /// Clear the Captcha in the session.
     async fn clear(session: &Session) {
-        if session.remove::<String>(CAPTCHA_KEY).await.is_err() {
+        clear_key(CAPTCHA_KEY, session).await
+    }
+
+    /// 验证指定命名空间的验证码，返回的布尔值代表验证码是否正确。其他命名空间的验证码不会通过验证。
+    ///
+    /// Verify the Captcha code of the given namespace, and return whether user's code is correct. Captchas of other
+    /// namespaces never pass.
+    async fn ver_for(namespace: &str, code: &str, session: &Session) -> bool {
+        ver_key(&namespaced_key(namespace), code, session).await
+    }
+
+    /// 清除Session中指定命名空间的验证码
+    ///
+    /// Clear the Captcha of the given namespace in the session.
+    async fn clear_for(namespace: &str, session: &Session) {
+        clear_key(&namespaced_key(namespace), session).await
+    }
+
+    /// 凭验证码编号验证以JSON签发的验证码，返回的布尔值代表验证码是否正确。无论是否正确，该验证码都会从Session中删除。
+    ///
+    /// 读取、删除和写回未验证的验证码不是原子操作：tower-sessions为每个请求单独加载Session，携带同一Session的并发请求可能都读到
+    /// 同一个答案，与之并发签发的验证码也可能在写回时丢失。需要严格保证每个答案只能使用一次时，请使用
+    /// [CaptchaStore](crate::store::CaptchaStore)。
+    ///
+    /// Verify the Captcha issued as JSON by the Captcha id, and return whether user's code is correct. The Captcha is
+    /// removed from the session whether the code is correct or not.
+    ///
+    /// Reading, removing and writing back the outstanding Captchas is not atomic: tower-sessions loads the session for
+    /// each request separately, so concurrent requests carrying the same session may all read the same answer, and a
+    /// Captcha issued concurrently may be lost on the write-back. Use a [CaptchaStore](crate::store::CaptchaStore) when
+    /// every answer must strictly be usable only once.
+    async fn ver_by_id(id: &str, code: &str, session: &Session) -> bool {
+        let mut outstanding = match session.get::<OutstandingCaptchas>(CAPTCHA_JSON_KEY).await {
+            Ok(Some(outstanding)) => outstanding,
+            _ => return false,
+        };
+        let valid = match outstanding.remove(id) {
+            Some((ans, expires_at)) => expires_at > unix_now() && ans.eq_ignore_ascii_case(code),
+            None => return false,
+        };
+
+        if session.insert(CAPTCHA_JSON_KEY, outstanding).await.is_err() {
             warn!("Exception occurs during clearing the session.")
         }
+        valid
     }
 }
 
 #[async_trait]
 impl<T: AbstractCaptcha + Send> CaptchaAxumTowerSessionExt for CaptchaUtil<T> {
-    type Error = anyhow::Error;
+    async fn out(&mut self, session: &Session) -> Result<Response, Error> {
+        out_to_key(&mut self.captcha_instance, CAPTCHA_KEY, session).await
+    }
 
-    async fn out(&mut self, session: &Session) -> Result<Response, Self::Error> {
-        let mut data = vec![];
-        self.captcha_instance.out(&mut data)?;
+    async fn out_for(&mut self, namespace: &str, session: &Session) -> Result<Response, Error> {
+        out_to_key(
+            &mut self.captcha_instance,
+            &namespaced_key(namespace),
+            session,
+        )
+        .await
+    }
+
+    async fn out_json(&mut self, session: &Session, ttl: Duration) -> Result<Response, Error> {
+        let mut image = vec![];
+        self.captcha_instance.out(&mut image)?;
+        let ans: String = self.captcha_instance.get_chars()?.iter().collect();
 
-        let ans: String = self.captcha_instance.get_chars().iter().collect();
-        session.insert(CAPTCHA_KEY, ans).await?;
+        let issued = CaptchaJson::from(IssuedCaptcha {
+            id: store::new_id(),
+            image,
+            content_type: self.captcha_instance.get_content_type(),
+            expires_at: store::expires_at(ttl)?,
+        });
 
-        let resp = Response::builder()
-            .header("Content-Type", self.captcha_instance.get_content_type())
-            .body(data.into())?;
-        Ok(resp)
+        // 顺便清理已过期的验证码，并限制数量，避免Session无限增长
+        let mut outstanding = session
+            .get::<OutstandingCaptchas>(CAPTCHA_JSON_KEY)
+            .await
+            .map_err(|e| Error::Storage(Box::new(e)))?
+            .unwrap_or_default();
+        let now = unix_now();
+        outstanding.retain(|_, (_, expires_at)| *expires_at > now);
+        while outstanding.len() >= MAX_OUTSTANDING {
+            let oldest = outstanding
+                .iter()
+                .min_by_key(|(_, (_, expires_at))| *expires_at)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => outstanding.remove(&id),
+                None => break,
+            };
+        }
+        outstanding.insert(issued.id.clone(), (ans, issued.expires_at));
+        session
+            .insert(CAPTCHA_JSON_KEY, outstanding)
+            .await
+            .map_err(|e| Error::Storage(Box::new(e)))?;
+
+        Ok(Json(issued).into_response())
     }
 }
 
 #[async_trait]
 impl CaptchaAxumTowerSessionStaticExt for CaptchaUtil {}
+
+/// 验证码的错误可以直接转换为响应，处理函数中可以直接使用`?`。[InvalidConfig](Error::InvalidConfig)以`400 Bad Request`响应，
+/// 其余错误以`500 Internal Server Error`响应。响应体只包含笼统的说明，错误详情只写入日志，不会泄露给客户端。
+///
+/// Captcha errors can be turned into responses, so `?` can be used in handlers directly.
+/// [InvalidConfig](Error::InvalidConfig) responds with `400 Bad Request`, and the other errors respond with
+/// `500 Internal Server Error`. The body only carries a generic message; the details are logged and never leak to the
+/// client.
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        match self {
+            Error::InvalidConfig(_) => {
+                warn!("Rejected the Captcha request: {}", self);
+                (StatusCode::BAD_REQUEST, "The Captcha request is not valid.").into_response()
+            }
+            _ => {
+                error!("Unable to serve the Captcha: {}", self);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Unable to serve the Captcha.",
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// 用户提交的验证码所在的位置，默认为查询参数`code`
+///
+/// 通过[Extension](axum::Extension)层设置，[VerifiedCaptcha]会从请求扩展中读取该配置：
+///
+/// The location of the code submitted by the user, `code` in the query by default. Set it with an
+/// [Extension](axum::Extension) layer, and [VerifiedCaptcha] will read it from the request extensions:
+///
+/// ```no_run
+/// use axum::{Extension, Router};
+/// use easy_captcha::extension::axum_tower_sessions::CaptchaCodeSource;
+///
+/// let router: Router = Router::new().layer(Extension(CaptchaCodeSource::Json("captcha".into())));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptchaCodeSource {
+    /// 查询参数中的字段
+    ///
+    /// A field in the query string.
+    Query(String),
+
+    /// `application/x-www-form-urlencoded`表单中的字段
+    ///
+    /// A field in the `application/x-www-form-urlencoded` form.
+    Form(String),
+
+    /// JSON请求体中的顶层字段，可以是字符串或数字
+    ///
+    /// A top-level field in the JSON body, either a string or a number.
+    Json(String),
+}
+
+impl Default for CaptchaCodeSource {
+    fn default() -> Self {
+        Self::Query("code".into())
+    }
+}
+
+/// [VerifiedCaptcha]验证的命名空间，参见[out_for](CaptchaAxumTowerSessionExt::out_for)
+///
+/// 通过[Extension](axum::Extension)层设置后，[VerifiedCaptcha]只接受保存在该命名空间中的验证码；未设置时验证由
+/// [out](CaptchaAxumTowerSessionExt::out)保存的验证码：
+///
+/// The namespace verified by [VerifiedCaptcha]; see [out_for](CaptchaAxumTowerSessionExt::out_for). Once set with an
+/// [Extension](axum::Extension) layer, [VerifiedCaptcha] only accepts the Captcha saved in that namespace; without it,
+/// the Captcha saved by [out](CaptchaAxumTowerSessionExt::out) is verified:
+///
+/// ```no_run
+/// use axum::{Extension, Router};
+/// use easy_captcha::extension::axum_tower_sessions::CaptchaNamespace;
+///
+/// let router: Router = Router::new().layer(Extension(CaptchaNamespace("login".into())));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptchaNamespace(pub String);
+
+/// [VerifiedCaptcha]拒绝请求的原因 The reasons why [VerifiedCaptcha] rejects a request
+#[derive(Debug)]
+pub enum CaptchaRejection {
+    /// 请求中没有Session，通常是因为没有启用`SessionManagerLayer`，响应`500 Internal Server Error`
+    ///
+    /// The request has no session, usually because `SessionManagerLayer` is not enabled; responds with
+    /// `500 Internal Server Error`.
+    MissingSession,
+
+    /// 请求中没有提交验证码，响应`400 Bad Request`
+    ///
+    /// The request doesn't carry the code; responds with `400 Bad Request`.
+    MissingCode,
+
+    /// 验证码不正确或已失效，响应`403 Forbidden`
+    ///
+    /// The code is wrong or expired; responds with `403 Forbidden`.
+    InvalidCode,
+
+    /// 验证通过后，内部的提取器拒绝了请求
+    ///
+    /// The inner extractor rejected the request after the code was verified.
+    Inner(Response),
+}
+
+impl IntoResponse for CaptchaRejection {
+    fn into_response(self) -> Response {
+        match self {
+            CaptchaRejection::MissingSession => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Can't extract session. Is `SessionManagerLayer` enabled?",
+            )
+                .into_response(),
+            CaptchaRejection::MissingCode => {
+                (StatusCode::BAD_REQUEST, "The Captcha code is missing.").into_response()
+            }
+            CaptchaRejection::InvalidCode => {
+                (StatusCode::FORBIDDEN, "The Captcha code is not valid.").into_response()
+            }
+            CaptchaRejection::Inner(response) => response,
+        }
+    }
+}
+
+/// 只有提交了正确验证码的请求才能通过的提取器
+///
+/// 验证码的位置由[CaptchaCodeSource]配置，命名空间由[CaptchaNamespace]配置。验证后Session中的验证码即被清除，无论验证是否通过，每个验证码只能使用一次。
+/// tower-sessions为每个请求单独加载Session，因此携带同一Session的并发请求仍可能同时通过验证，如需严格防止，
+/// 请使用[CaptchaStore](crate::store::CaptchaStore)或[token](crate::token)模块。
+/// 验证通过后会再以内部的提取器`E`提取请求，因此表单或JSON中的其他字段仍然可以使用；`E`默认为请求体本身。
+///
+/// An extractor that only accepts requests carrying the correct code.
+///
+/// The location of the code is configured by [CaptchaCodeSource], and the namespace by [CaptchaNamespace]. The
+/// Captcha is cleared from the session once it is checked, whether it passes or not, so each Captcha can only be used
+/// once. tower-sessions loads the session for each request separately, so concurrent requests carrying the same
+/// session may still pass together; use a [CaptchaStore](crate::store::CaptchaStore) or the [token](crate::token)
+/// module to rule that out strictly. After verification the request is extracted again by the inner extractor `E`, so
+/// the other fields of the form or JSON are still available; `E` is the request body itself by default.
+///
+/// ```no_run
+/// use axum::Form;
+/// use easy_captcha::extension::axum_tower_sessions::VerifiedCaptcha;
+/// use std::collections::HashMap;
+///
+/// async fn login(VerifiedCaptcha(Form(form)): VerifiedCaptcha<Form<HashMap<String, String>>>) {
+///     // 验证码已通过 The code is verified
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VerifiedCaptcha<E = Body>(pub E);
+
+#[async_trait]
+impl<S, E> FromRequest<S> for VerifiedCaptcha<E>
+where
+    S: Send + Sync,
+    E: FromRequest<S>,
+{
+    type Rejection = CaptchaRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let session = req
+            .extensions()
+            .get::<Session>()
+            .cloned()
+            .ok_or(CaptchaRejection::MissingSession)?;
+        let source = req
+            .extensions()
+            .get::<CaptchaCodeSource>()
+            .cloned()
+            .unwrap_or_default();
+        let key = match req.extensions().get::<CaptchaNamespace>() {
+            Some(CaptchaNamespace(namespace)) => namespaced_key(namespace),
+            None => CAPTCHA_KEY.to_string(),
+        };
+
+        let (code, req) = match &source {
+            CaptchaCodeSource::Query(field) => {
+                let code = Query::<HashMap<String, String>>::try_from_uri(req.uri())
+                    .ok()
+                    .and_then(|Query(mut query)| query.remove(field));
+                (code, req)
+            }
+            CaptchaCodeSource::Form(field) | CaptchaCodeSource::Json(field) => {
+                // 请求体只能读取一次，这里先缓存下来，供内部的提取器再次使用
+                let (parts, body) = req.into_parts();
+                let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+                    .await
+                    .map_err(|e| CaptchaRejection::Inner(e.into_response()))?;
+                let copy = || Request::from_parts(parts.clone(), Body::from(bytes.clone()));
+
+                let code = if let CaptchaCodeSource::Form(_) = source {
+                    Form::<HashMap<String, String>>::from_request(copy(), state)
+                        .await
+                        .ok()
+                        .and_then(|Form(mut form)| form.remove(field))
+                } else {
+                    Json::<serde_json::Value>::from_request(copy(), state)
+                        .await
+                        .ok()
+                        .and_then(|Json(json)| match json.get(field)? {
+                            serde_json::Value::String(code) => Some(code.clone()),
+                            serde_json::Value::Number(code) => Some(code.to_string()),
+                            _ => None,
+                        })
+                };
+                (code, copy())
+            }
+        };
+
+        let code = code.ok_or(CaptchaRejection::MissingCode)?;
+        if !take_key(&key, &code, &session).await {
+            return Err(CaptchaRejection::InvalidCode);
+        }
+
+        E::from_request(req, state)
+            .await
+            .map(VerifiedCaptcha)
+            .map_err(|e| CaptchaRejection::Inner(e.into_response()))
+    }
+}
+
+/// 生成验证码并保存至Session的处理函数
+async fn get_captcha<T: AbstractCaptcha + Send>(session: Session) -> Result<Response, Error> {
+    let mut captcha: CaptchaUtil<T> = CaptchaUtil::new();
+    captcha.out(&session).await
+}
+
+/// 验证验证码的处理函数，验证通过时响应`204 No Content`
+async fn verify_captcha(_: VerifiedCaptcha) -> StatusCode {
+    StatusCode::NO_CONTENT
+}
+
+/// 预先配置好验证码接口的路由 A Router with the Captcha routes preconfigured
+///
+/// - `GET /captcha`：生成验证码图片，并将答案保存至Session
+/// - `GET|POST /captcha/verify`：以[VerifiedCaptcha]验证用户提交的验证码，通过时响应`204 No Content`
+///
+/// 路由本身不包含Session层，需要在合并后的路由上添加`SessionManagerLayer`：
+///
+/// - `GET /captcha`: generate the Captcha image and save the answer into the session.
+/// - `GET|POST /captcha/verify`: verify the code with [VerifiedCaptcha], responding with `204 No Content` when it
+///   passes.
+///
+/// The router doesn't contain the session layer; add `SessionManagerLayer` to the merged router:
+///
+/// ```no_run
+/// use axum::Router;
+/// use easy_captcha::captcha::gif::GifCaptcha;
+/// use easy_captcha::extension::axum_tower_sessions::captcha_router;
+/// use tower_sessions::{MemoryStore, SessionManagerLayer};
+///
+/// let router: Router = Router::new()
+///     .merge(captcha_router::<GifCaptcha>())
+///     .layer(SessionManagerLayer::new(MemoryStore::default()));
+/// ```
+pub fn captcha_router<T: AbstractCaptcha + Send + 'static>() -> Router {
+    Router::new()
+        .route("/captcha", get(get_captcha::<T>))
+        .route("/captcha/verify", get(verify_captcha).post(verify_captcha))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::spec::SpecCaptcha;
+    use axum::extract::Path;
+    use axum::routing::post;
+    use axum::Extension;
+    use tower::ServiceExt;
+    use tower_sessions::{MemoryStore, SessionManagerLayer};
+
+    async fn send(router: &Router, req: axum::http::request::Builder, body: Body) -> Response {
+        router
+            .clone()
+            .oneshot(req.body(body).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn session_cookie(resp: &Response) -> String {
+        let cookie = resp.headers()[header::SET_COOKIE].to_str().unwrap();
+        cookie.split(';').next().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn it_serves_the_captcha_router() {
+        let router = Router::new()
+            .merge(captcha_router::<SpecCaptcha>())
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "image/png");
+        let cookie = session_cookie(&resp);
+
+        let verify = |uri: &str| Request::get(uri).header(header::COOKIE, &cookie);
+        let resp = send(&router, verify("/captcha/verify"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = send(&router, verify("/captcha/verify?code=wrong"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn it_keeps_namespaces_apart() {
+        async fn get_captcha(
+            session: Session,
+            Path(namespace): Path<String>,
+        ) -> Result<Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out_for(&namespace, &session).await
+        }
+
+        async fn verify(
+            session: Session,
+            Path((namespace, code)): Path<(String, String)>,
+        ) -> StatusCode {
+            if CaptchaUtil::ver_for(&namespace, &code, &session).await {
+                CaptchaUtil::clear_for(&namespace, &session).await;
+                StatusCode::OK
+            } else {
+                StatusCode::FORBIDDEN
+            }
+        }
+
+        let router = Router::new()
+            .route("/captcha/:namespace", get(get_captcha))
+            .route("/verify/:namespace/:code", get(verify))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha/login"), Body::empty()).await;
+        let cookie = session_cookie(&resp);
+        let request = |uri: &str| Request::get(uri).header(header::COOKIE, &cookie);
+
+        // 评论验证码不会覆盖登录验证码 The comment Captcha doesn't overwrite the login one
+        let resp = send(&router, request("/captcha/comment"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = send(&router, request("/verify/register/wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = send(&router, request("/verify/login/wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = send(&router, request("/verify/login/wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = send(&router, request("/verify/comment/wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn it_verifies_outstanding_json_captchas() {
+        async fn get_captcha(session: Session) -> Result<Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out_json(&session, Duration::from_secs(60)).await
+        }
+
+        async fn verify(session: Session, Path((id, code)): Path<(String, String)>) -> StatusCode {
+            if CaptchaUtil::ver_by_id(&id, &code, &session).await {
+                StatusCode::OK
+            } else {
+                StatusCode::FORBIDDEN
+            }
+        }
+
+        let router = Router::new()
+            .route("/captcha", get(get_captcha))
+            .route("/verify/:id/:code", get(verify))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha"), Body::empty()).await;
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        let cookie = session_cookie(&resp);
+        let json = |resp: Response| async {
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<CaptchaJson>(&body).unwrap()
+        };
+        let first = json(resp).await;
+        assert!(first.image.starts_with("data:image/png;base64,"));
+
+        // 同一用户可以同时持有多个验证码 A user may hold several Captchas at the same time
+        let second = Request::get("/captcha").header(header::COOKIE, &cookie);
+        let second = json(send(&router, second, Body::empty()).await).await;
+        assert_ne!(first.id, second.id);
+
+        let verify = |id: &str, code: &str| {
+            Request::get(format!("/verify/{}/{}", id, code)).header(header::COOKIE, &cookie)
+        };
+        let resp = send(&router, verify(&second.id, "wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = send(&router, verify(&first.id, "wrong"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = send(&router, verify(&first.id, "wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn it_takes_json_captchas_across_requests() {
+        async fn get_captcha(session: Session) -> Result<Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out_json(&session, Duration::from_secs(60)).await
+        }
+
+        async fn verify(session: Session, Path((id, code)): Path<(String, String)>) -> StatusCode {
+            if CaptchaUtil::ver_by_id(&id, &code, &session).await {
+                StatusCode::OK
+            } else {
+                StatusCode::FORBIDDEN
+            }
+        }
+
+        let router = Router::new()
+            .route("/captcha", get(get_captcha))
+            .route("/verify/:id/:code", get(verify))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha"), Body::empty()).await;
+        let cookie = session_cookie(&resp);
+        let id = |resp: Response| async {
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<CaptchaJson>(&body).unwrap().id
+        };
+        let first = id(resp).await;
+        let second = Request::get("/captcha").header(header::COOKIE, &cookie);
+        let second = id(send(&router, second, Body::empty()).await).await;
+
+        // 每个请求单独加载Session，前一个请求删除的答案在后一个请求中同样不存在
+        let verify = |id: &str| {
+            Request::get(format!("/verify/{}/wr7ba", id)).header(header::COOKIE, &cookie)
+        };
+        let resp = send(&router, verify(&first), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = send(&router, verify(&first), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // 写回时保留了其他未验证的验证码
+        let resp = send(&router, verify(&second), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn it_caps_outstanding_json_captchas() {
+        let session = Session::new(None, std::sync::Arc::new(MemoryStore::default()), None);
+        let mut ids = Vec::new();
+        for i in 0..=MAX_OUTSTANDING as u64 {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            let resp = captcha
+                .out_json(&session, Duration::from_secs(60 + i))
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            ids.push(serde_json::from_slice::<CaptchaJson>(&body).unwrap().id);
+        }
+
+        let outstanding = session
+            .get::<OutstandingCaptchas>(CAPTCHA_JSON_KEY)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outstanding.len(), MAX_OUTSTANDING);
+
+        // 最早过期的验证码被丢弃 The one expiring first is dropped
+        assert!(!CaptchaUtil::ver_by_id(&ids[0], "wr7ba", &session).await);
+        assert!(CaptchaUtil::ver_by_id(&ids[1], "wr7ba", &session).await);
+        assert!(CaptchaUtil::ver_by_id(&ids[MAX_OUTSTANDING], "wr7ba", &session).await);
+    }
+
+    #[tokio::test]
+    async fn it_verifies_codes_in_the_body() {
+        async fn get_captcha(session: Session) -> Result<Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out(&session).await
+        }
+
+        async fn login(
+            VerifiedCaptcha(Json(json)): VerifiedCaptcha<Json<serde_json::Value>>,
+        ) -> String {
+            json["user"].as_str().unwrap().to_string()
+        }
+
+        let router = Router::new()
+            .route("/captcha", get(get_captcha))
+            .route("/login", post(login))
+            .layer(Extension(CaptchaCodeSource::Json("captcha".into())))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha"), Body::empty()).await;
+        let cookie = session_cookie(&resp);
+
+        let login = || {
+            Request::post("/login")
+                .header(header::COOKIE, &cookie)
+                .header(header::CONTENT_TYPE, "application/json")
+        };
+        let body = || Body::from(r#"{"user": "alice", "captcha": "wr7ba"}"#);
+        let resp = send(&router, login(), body()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let user = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(user, "alice");
+
+        // 验证码只能使用一次 Each Captcha can only be used once
+        let resp = send(&router, login(), body()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn it_takes_the_answer_on_wrong_codes() {
+        async fn get_captcha(session: Session) -> Result<Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out(&session).await
+        }
+
+        let router = Router::new()
+            .route("/captcha", get(get_captcha))
+            .route("/verify", get(verify_captcha))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha"), Body::empty()).await;
+        let cookie = session_cookie(&resp);
+
+        // 两个请求携带同一Cookie，第一次提交错误的验证码后答案即失效
+        let verify = |uri: &str| Request::get(uri).header(header::COOKIE, &cookie);
+        let resp = send(&router, verify("/verify?code=wrong"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = send(&router, verify("/verify?code=wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn it_verifies_the_configured_namespace() {
+        async fn get_captcha(
+            session: Session,
+            Path(namespace): Path<String>,
+        ) -> Result<Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out_for(&namespace, &session).await
+        }
+
+        let router = Router::new()
+            .route("/captcha/:namespace", get(get_captcha))
+            .route("/verify", get(verify_captcha))
+            .layer(Extension(CaptchaNamespace("login".into())))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+
+        let resp = send(&router, Request::get("/captcha/comment"), Body::empty()).await;
+        let cookie = session_cookie(&resp);
+        let request = |uri: &str| Request::get(uri).header(header::COOKIE, &cookie);
+
+        // 其他命名空间的验证码不会通过验证
+        let resp = send(&router, request("/verify?code=wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        send(&router, request("/captcha/login"), Body::empty()).await;
+        let resp = send(&router, request("/verify?code=wr7ba"), Body::empty()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn it_hides_error_details_from_clients() {
+        let body = |resp: Response| async {
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        let resp = Error::InvalidConfig("secret detail".into()).into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(!body(resp).await.contains("secret"));
+
+        let resp = Error::Storage("secret detail".into()).into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!body(resp).await.contains("secret"));
+    }
+}
@@ -2,12 +2,28 @@
 //!
 //! 针对于Web框架的扩展（如axum-tower_session这样的组合）
 
+#[cfg(feature = "actix-session")]
+pub mod actix_session;
 #[cfg(feature = "axum-tower-sessions")]
 pub mod axum_tower_sessions;
+#[cfg(feature = "poem")]
+pub mod poem;
+#[cfg(feature = "rocket")]
+pub mod rocket;
+#[cfg(any(feature = "actix-session", feature = "poem", feature = "rocket"))]
+mod sealed;
+#[cfg(feature = "tower")]
+pub mod tower;
 
 use crate::base::captcha::AbstractCaptcha;
 use crate::captcha::spec::SpecCaptcha;
-use crate::{CaptchaFont, NewCaptcha};
+use crate::store::{self, CaptchaJson, CaptchaStore, IssuedCaptcha};
+use crate::{CaptchaFont, CaptchaType, Charset, Error, FontSource, NewCaptcha};
+use rand::RngCore;
+use std::time::Duration;
+
+/// 验证码答案在Session中的键
+pub(crate) const CAPTCHA_KEY: &str = "ez-captcha";
 
 /// 验证码工具类 - Captcha Utils
 ///
@@ -43,4 +59,220 @@ impl<T: AbstractCaptcha> NewCaptcha for CaptchaUtil<T> {
             captcha_instance: T::with_all(width, height, len, font, font_size),
         }
     }
+
+    fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        Self {
+            captcha_instance: T::with_rng(rng),
+        }
+    }
+}
+
+impl<T: AbstractCaptcha> CaptchaUtil<T> {
+    /// 设置验证码的文本类型，例如只使用数字
+    ///
+    /// Set the character type of the Captcha, e.g. numbers only.
+    ///
+    /// ```
+    /// use easy_captcha::extension::CaptchaUtil;
+    /// use easy_captcha::{CaptchaType, NewCaptcha};
+    ///
+    /// let mut captcha: CaptchaUtil = CaptchaUtil::new();
+    /// captcha.set_char_type(CaptchaType::TypeOnlyNumber);
+    /// ```
+    pub fn set_char_type(&mut self, char_type: CaptchaType) {
+        self.captcha_instance.set_char_type(char_type)
+    }
+
+    /// 以链式调用的方式设置验证码的文本类型
+    ///
+    /// Set the character type of the Captcha in a chained call.
+    pub fn with_char_type(mut self, char_type: CaptchaType) -> Self {
+        self.set_char_type(char_type);
+        self
+    }
+
+    /// 设置验证码的字符集，可以使用自定义的字符
+    ///
+    /// Set the character set of the Captcha, which may consist of custom characters.
+    ///
+    /// ```
+    /// use easy_captcha::extension::CaptchaUtil;
+    /// use easy_captcha::{CaptchaType, Charset, NewCaptcha};
+    ///
+    /// let charset = Charset::from(CaptchaType::TypeNumAndUpper).without("7S2Z").unwrap();
+    /// let mut captcha: CaptchaUtil = CaptchaUtil::new();
+    /// captcha.set_charset(charset);
+    /// ```
+    pub fn set_charset(&mut self, charset: Charset) {
+        self.captcha_instance.set_charset(charset)
+    }
+
+    /// 以链式调用的方式设置验证码的字符集
+    ///
+    /// Set the character set of the Captcha in a chained call.
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.set_charset(charset);
+        self
+    }
+
+    /// 设置验证码使用的字体，可以是内置字体、系统字体或由文件、数据加载的字体
+    ///
+    /// Set the font used by the Captcha, which may be a built-in font, a system font, or a font loaded from a file or
+    /// data.
+    pub fn set_font(&mut self, font: impl Into<FontSource>) {
+        self.captcha_instance.set_font(font)
+    }
+
+    /// 设置验证码使用的多个字体，每个字符依次轮换使用其中的字体
+    ///
+    /// Set several fonts used by the Captcha; characters rotate among them in turn.
+    pub fn set_fonts(&mut self, fonts: Vec<FontSource>) {
+        self.captcha_instance.set_fonts(fonts)
+    }
+
+    /// 设置验证码的字体大小
+    ///
+    /// Set the font size of the Captcha.
+    pub fn set_font_size(&mut self, size: f32) {
+        self.captcha_instance.set_font_size(size)
+    }
+
+    /// 生成验证码图像，并以随机的验证码编号将答案保存到存储中，答案在`ttl`后过期
+    ///
+    /// Generate the Captcha image, and save the answer into the storage by a random Captcha id; the answer expires
+    /// after `ttl`.
+    ///
+    /// ```
+    /// use easy_captcha::extension::CaptchaUtil;
+    /// use easy_captcha::store::MemoryStore;
+    /// use easy_captcha::NewCaptcha;
+    /// use std::time::Duration;
+    ///
+    /// # tokio_test(async {
+    /// let store = MemoryStore::new();
+    /// let mut captcha: CaptchaUtil = CaptchaUtil::new();
+    /// let issued = captcha.issue(&store, Duration::from_secs(300)).await.unwrap();
+    /// // 将issued.id和issued.image返回给客户端 Return issued.id and issued.image to the client
+    ///
+    /// assert!(!CaptchaUtil::verify(&store, &issued.id, "wrong").await.unwrap());
+    /// # });
+    /// # fn tokio_test(f: impl std::future::Future) {
+    /// #     tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(f);
+    /// # }
+    /// ```
+    pub async fn issue(
+        &mut self,
+        store: &(impl CaptchaStore + ?Sized),
+        ttl: Duration,
+    ) -> Result<IssuedCaptcha, Error> {
+        let mut image = Vec::new();
+        self.captcha_instance.out(&mut image)?;
+        let answer: String = self.captcha_instance.get_chars()?.iter().collect();
+
+        let expires_at = store::expires_at(ttl)?;
+        let id = store::new_id();
+        store.put(&id, &answer, ttl).await?;
+
+        Ok(IssuedCaptcha {
+            id,
+            image,
+            content_type: self.captcha_instance.get_content_type(),
+            expires_at,
+        })
+    }
+
+    /// 签发验证码并以JSON返回，参见[issue](Self::issue)和[CaptchaJson]
+    ///
+    /// Issue the Captcha and return it as JSON; see [issue](Self::issue) and [CaptchaJson].
+    pub async fn issue_json(
+        &mut self,
+        store: &(impl CaptchaStore + ?Sized),
+        ttl: Duration,
+    ) -> Result<CaptchaJson, Error> {
+        self.issue(store, ttl).await.map(CaptchaJson::from)
+    }
+}
+
+impl CaptchaUtil {
+    /// 凭验证码编号验证验证码（不区分大小写），返回的布尔值代表验证码是否正确。无论是否正确，答案都会从存储中删除，以防止重复尝试。
+    ///
+    /// Verify the Captcha code by the Captcha id, ignoring ASCII case, and return whether user's code is correct. The
+    /// answer is removed from the storage whether the code is correct or not, to prevent repeated attempts.
+    pub async fn verify(
+        store: &(impl CaptchaStore + ?Sized),
+        id: &str,
+        code: &str,
+    ) -> Result<bool, Error> {
+        Ok(match store.take(id).await? {
+            Some(answer) => answer.eq_ignore_ascii_case(code),
+            None => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[tokio::test]
+    async fn it_verifies_by_id() {
+        let store = MemoryStore::new();
+        let mut captcha: CaptchaUtil = CaptchaUtil::with_seed(42);
+        let issued = captcha
+            .issue(&store, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(issued.content_type, "image/png");
+
+        assert!(CaptchaUtil::verify(&store, &issued.id, "wr7ba")
+            .await
+            .unwrap());
+        assert!(!CaptchaUtil::verify(&store, &issued.id, "WR7BA")
+            .await
+            .unwrap());
+
+        let issued = captcha
+            .issue(&store, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(!CaptchaUtil::verify(&store, &issued.id, "wrong")
+            .await
+            .unwrap());
+        assert!(!CaptchaUtil::verify(&store, &issued.id, "WR7BA")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn it_issues_json() {
+        let store = MemoryStore::new();
+        let mut captcha: CaptchaUtil = CaptchaUtil::with_seed(42);
+        let json = captcha
+            .issue_json(&store, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(json.mime, "image/png");
+        assert!(json.image.starts_with("data:image/png;base64,"));
+
+        let value = serde_json::to_value(&json).unwrap();
+        assert_eq!(value["id"], json.id.as_str());
+        assert_eq!(value["expires_at"], json.expires_at);
+        assert_eq!(serde_json::from_value::<CaptchaJson>(value).unwrap(), json);
+
+        assert!(CaptchaUtil::verify(&store, &json.id, "wr7ba")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_ttl_beyond_the_clock() {
+        let store = MemoryStore::new();
+        let mut captcha: CaptchaUtil = CaptchaUtil::with_seed(42);
+        assert!(matches!(
+            captcha.issue(&store, Duration::MAX).await,
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(store.is_empty());
+    }
 }
@@ -0,0 +1,230 @@
+//! Poem 组合
+//!
+//! - Poem: [poem](https://docs.rs/poem)，需要使用其`session`特性提供的Session中间件
+//!
+//! 使用`CookieSession`时Session的内容保存在客户端的Cookie里，必须使用`CookieConfig::private`加密Cookie，否则客户端可以直接读出答案
+//! （`CookieConfig::signed`只防篡改，不能隐藏内容）。
+//!
+//! 验证时答案会从Session中取出，无论验证码是否正确，每个答案只能验证一次。客户端可以重新提交取出答案前的旧Cookie，因此每个答案都带有一个
+//! 随机数，验证时通过[ReplayGuard]记录已使用过的随机数。[ver](CaptchaPoemSessionStaticExt::ver)使用进程内的
+//! [MemoryReplayGuard](crate::token::MemoryReplayGuard)，多实例部署时请使用
+//! [ver_with_guard](CaptchaPoemSessionStaticExt::ver_with_guard)并提供共享存储的防重放检查。
+//!
+//! With `CookieSession` the session lives in a client cookie, so the cookie must be encrypted with
+//! `CookieConfig::private`, or the client can read the answer directly (`CookieConfig::signed` only prevents tampering
+//! and doesn't hide the content).
+//!
+//! The answer is taken out of the session on verification, so every answer can be verified only once, whether the
+//! code is correct or not. The client may resend the old cookie from before the answer was taken; hence every answer
+//! carries a nonce, and the used nonces are recorded through a [ReplayGuard] on verification.
+//! [ver](CaptchaPoemSessionStaticExt::ver) uses an in-process [MemoryReplayGuard](crate::token::MemoryReplayGuard);
+//! for multi-instance deployments, use [ver_with_guard](CaptchaPoemSessionStaticExt::ver_with_guard) with a replay
+//! guard backed by a shared storage.
+//!
+//! ```no_run
+//! use easy_captcha::captcha::gif::GifCaptcha;
+//! use easy_captcha::extension::poem::{CaptchaPoemSessionExt, CaptchaPoemSessionStaticExt};
+//! use easy_captcha::extension::CaptchaUtil;
+//! use easy_captcha::{Error, NewCaptcha};
+//! use poem::session::{CookieConfig, MemoryStorage, ServerSession, Session};
+//! use poem::web::Path;
+//! use poem::{get, handler, EndpointExt, Response, Route};
+//!
+//! /// 接口：获取验证码
+//! #[handler]
+//! fn get_captcha(session: &Session) -> Result<Response, Error> {
+//!     CaptchaUtil::<GifCaptcha>::new().out(session)
+//! }
+//!
+//! /// 接口：验证验证码
+//! #[handler]
+//! async fn verify_captcha(session: &Session, Path(code): Path<String>) -> &'static str {
+//!     if CaptchaUtil::ver(&code, session).await {
+//!         "Your code is valid, thank you."
+//!     } else {
+//!         "Your code is not valid, I'm sorry."
+//!     }
+//! }
+//!
+//! let app = Route::new()
+//!     .at("/captcha", get(get_captcha))
+//!     .at("/verify/:code", get(verify_captcha))
+//!     .with(ServerSession::new(CookieConfig::default(), MemoryStorage::new()));
+//! ```
+
+use crate::extension::sealed::{SealedAnswer, DEFAULT_GUARD};
+use crate::extension::{CaptchaUtil, CAPTCHA_KEY};
+use crate::token::ReplayGuard;
+use crate::{AbstractCaptcha, Error};
+use async_trait::async_trait;
+use poem::error::ResponseError;
+use poem::http::StatusCode;
+use poem::session::Session;
+use poem::Response;
+
+/// Poem
+pub trait CaptchaPoemSessionExt {
+    /// 将验证码图片写入响应，并将用户的验证码信息保存至Session中
+    ///
+    /// Write the Captcha Image into the response and save the Captcha information into the user's Session.
+    fn out(&mut self, session: &Session) -> Result<Response, Error>;
+}
+
+/// Poem - 静态方法
+#[async_trait]
+pub trait CaptchaPoemSessionStaticExt {
+    /// 验证验证码（不区分大小写），返回的布尔值代表验证码是否正确。无论是否正确，答案都会从Session中取出，并使用进程内的防重放检查。
+    ///
+    /// Verify the Captcha code, ignoring ASCII case, and return whether user's code is correct. The answer is taken
+    /// out of the session whether the code is correct or not, and an in-process replay guard is used.
+    async fn ver(code: &str, session: &Session) -> bool {
+        Self::ver_with_guard(code, session, &*DEFAULT_GUARD).await
+    }
+
+    /// 验证验证码，并使用给定的防重放检查记录已使用过的答案，参见[ver](Self::ver)
+    ///
+    /// Verify the Captcha code, recording the used answers with the given replay guard; see [ver](Self::ver).
+    async fn ver_with_guard(code: &str, session: &Session, guard: &dyn ReplayGuard) -> bool {
+        let sealed = session
+            .get::<String>(CAPTCHA_KEY)
+            .and_then(|value| SealedAnswer::parse(&value));
+        session.remove(CAPTCHA_KEY);
+        match sealed {
+            Some(sealed) => match sealed.open(guard).await {
+                Some(ans) => ans.eq_ignore_ascii_case(code),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// 清除Session中的验证码
+    ///
+    /// Clear the Captcha in the session.
+    fn clear(session: &Session) {
+        session.remove(CAPTCHA_KEY);
+    }
+}
+
+impl<T: AbstractCaptcha> CaptchaPoemSessionExt for CaptchaUtil<T> {
+    fn out(&mut self, session: &Session) -> Result<Response, Error> {
+        let mut data = vec![];
+        self.captcha_instance.out(&mut data)?;
+
+        let ans: String = self.captcha_instance.get_chars()?.iter().collect();
+        session.set(CAPTCHA_KEY, SealedAnswer::new(ans).to_string());
+
+        Ok(Response::builder()
+            .content_type(self.captcha_instance.get_content_type())
+            .body(data))
+    }
+}
+
+impl CaptchaPoemSessionStaticExt for CaptchaUtil {}
+
+/// 验证码的错误会以`500 Internal Server Error`响应，处理函数中可以直接使用`?`
+///
+/// Captcha errors respond with `500 Internal Server Error`, so `?` can be used in handlers directly.
+impl ResponseError for Error {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::gif::GifCaptcha;
+    use crate::NewCaptcha;
+    use poem::http::header;
+    use poem::session::{CookieConfig, CookieSession, MemoryStorage, ServerSession};
+    use poem::test::TestClient;
+    use poem::web::cookie::CookieKey;
+    use poem::web::Path;
+    use poem::{get, handler, EndpointExt, Route};
+
+    #[handler]
+    fn get_captcha(session: &Session) -> Result<Response, Error> {
+        let mut captcha: CaptchaUtil<GifCaptcha> = CaptchaUtil::with_seed(42);
+        captcha.out(session)
+    }
+
+    #[handler]
+    async fn verify_captcha(session: &Session, Path(code): Path<String>) -> StatusCode {
+        if CaptchaUtil::ver(&code, session).await {
+            StatusCode::OK
+        } else {
+            StatusCode::FORBIDDEN
+        }
+    }
+
+    #[tokio::test]
+    async fn it_verifies_codes_in_the_session() {
+        let app = Route::new()
+            .at("/captcha", get(get_captcha))
+            .at("/verify/:code", get(verify_captcha))
+            .with(CookieSession::new(CookieConfig::private(
+                CookieKey::generate(),
+            )));
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/captcha").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("image/gif");
+        let cookie = resp.0.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        // 私有Cookie经过加密，客户端无法读出答案
+        assert!(!cookie.contains("WR7BA"));
+
+        let verify = |code: &str| {
+            cli.get(format!("/verify/{}", code))
+                .header(header::COOKIE, cookie.clone())
+        };
+        verify("wr7ba").send().await.assert_status_is_ok();
+
+        // 重新提交的旧Cookie会被拒绝
+        verify("wr7ba")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn it_takes_the_answer_on_wrong_codes() {
+        let app = Route::new()
+            .at("/captcha", get(get_captcha))
+            .at("/verify/:code", get(verify_captcha))
+            .with(ServerSession::new(
+                CookieConfig::default(),
+                MemoryStorage::new(),
+            ));
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/captcha").send().await;
+        let cookie = resp.0.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+
+        let verify = |code: &str| {
+            cli.get(format!("/verify/{}", code))
+                .header(header::COOKIE, cookie.clone())
+        };
+        verify("wrong")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        verify("wr7ba")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,238 @@
+//! Rocket 私有Cookie
+//!
+//! - Rocket: [rocket](https://docs.rs/rocket)
+//!
+//! 答案保存在加密的私有Cookie中，需要启用Rocket的`secrets`特性（本特性已自动启用）并在生产环境中配置`secret_key`。答案会在5分钟后过期。
+//!
+//! Cookie保存在客户端，清除Cookie并不能阻止客户端重新提交截获的旧Cookie。因此每个答案都带有一个随机数，[CaptchaAnswer]会通过
+//! [ReplayGuard]记录已使用过的随机数，拒绝重复提交的Cookie。默认使用进程内的
+//! [MemoryReplayGuard](crate::token::MemoryReplayGuard)，多实例部署时请通过`manage`注册一个共享存储的[CaptchaReplayGuard]。
+//!
+//! The cookie lives on the client, and clearing it can't stop the client from sending a captured old cookie again.
+//! Hence every answer carries a nonce, and [CaptchaAnswer] records the used nonces through a [ReplayGuard] to reject
+//! resent cookies. An in-process [MemoryReplayGuard](crate::token::MemoryReplayGuard) is used by default; for
+//! multi-instance deployments, register a [CaptchaReplayGuard] backed by a shared storage with `manage`.
+//!
+//! ```no_run
+//! # #[macro_use] extern crate rocket;
+//! use easy_captcha::captcha::gif::GifCaptcha;
+//! use easy_captcha::extension::rocket::CaptchaAnswer;
+//! use easy_captcha::extension::CaptchaUtil;
+//! use easy_captcha::NewCaptcha;
+//!
+//! /// 接口：获取验证码
+//! #[get("/captcha")]
+//! fn get_captcha() -> CaptchaUtil<GifCaptcha> {
+//!     CaptchaUtil::new()
+//! }
+//!
+//! /// 接口：验证验证码
+//! #[get("/verify?<code>")]
+//! fn verify_captcha(captcha: CaptchaAnswer, code: &str) -> &'static str {
+//!     if captcha.ver(code) {
+//!         "Your code is valid, thank you."
+//!     } else {
+//!         "Your code is not valid, I'm sorry."
+//!     }
+//! }
+//!
+//! #[launch]
+//! fn rocket() -> _ {
+//!     rocket::build().mount("/", routes![get_captcha, verify_captcha])
+//! }
+//! ```
+
+use crate::extension::sealed::{SealedAnswer, ANSWER_TTL, DEFAULT_GUARD};
+use crate::extension::{CaptchaUtil, CAPTCHA_KEY};
+use crate::token::ReplayGuard;
+use crate::AbstractCaptcha;
+use log::error;
+use rocket::http::{ContentType, Cookie, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder};
+use rocket::{Request, Response};
+use std::io::Cursor;
+
+/// 将验证码图片写入响应，并将答案保存至私有Cookie中
+///
+/// Write the Captcha image into the response and save the answer into a private cookie.
+impl<'r, 'o: 'r, T: AbstractCaptcha> Responder<'r, 'o> for CaptchaUtil<T> {
+    fn respond_to(mut self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut data = vec![];
+        let ans = self
+            .captcha_instance
+            .out(&mut data)
+            .and_then(|_| self.captcha_instance.get_chars())
+            .map_err(|e| {
+                error!("Unable to generate the Captcha: {}", e);
+                Status::InternalServerError
+            })?;
+
+        let ans: String = ans.iter().collect();
+        req.cookies().add_private(
+            Cookie::build((CAPTCHA_KEY, SealedAnswer::new(ans).to_string()))
+                .max_age(rocket::time::Duration::seconds(ANSWER_TTL.as_secs() as i64)),
+        );
+
+        let content_type = ContentType::parse_flexible(&self.captcha_instance.get_content_type())
+            .unwrap_or(ContentType::Binary);
+        Response::build()
+            .header(content_type)
+            .sized_body(data.len(), Cursor::new(data))
+            .ok()
+    }
+}
+
+/// 请求守卫使用的防重放检查 The replay protection used by the request guard
+///
+/// 通过`manage`注册后，[CaptchaAnswer]会使用它记录已使用过的随机数；未注册时使用进程内的
+/// [MemoryReplayGuard](crate::token::MemoryReplayGuard)。
+///
+/// Once registered with `manage`, [CaptchaAnswer] records the used nonces with it; an in-process
+/// [MemoryReplayGuard](crate::token::MemoryReplayGuard) is used when none is registered.
+///
+/// ```no_run
+/// use easy_captcha::extension::rocket::CaptchaReplayGuard;
+/// use easy_captcha::token::MemoryReplayGuard;
+///
+/// let rocket = rocket::build().manage(CaptchaReplayGuard::new(MemoryReplayGuard::new()));
+/// ```
+pub struct CaptchaReplayGuard(Box<dyn ReplayGuard>);
+
+impl CaptchaReplayGuard {
+    /// 使用给定的防重放检查创建
+    ///
+    /// Create with the given replay guard.
+    pub fn new(guard: impl ReplayGuard + 'static) -> Self {
+        Self(Box::new(guard))
+    }
+}
+
+/// 验证码答案的请求守卫 The request guard of the Captcha answer
+///
+/// 取出私有Cookie中的答案并立即清除该Cookie，同时将答案的随机数标记为已使用，重新提交的旧Cookie会被拒绝，因此每个答案只能验证一次。
+///
+/// Take out the answer in the private cookie and clear the cookie immediately. The nonce of the answer is marked as
+/// used as well, so a resent old cookie is rejected, and every answer can be verified only once.
+#[derive(Debug)]
+pub struct CaptchaAnswer {
+    answer: Option<String>,
+}
+
+impl CaptchaAnswer {
+    /// 验证验证码，返回的布尔值代表验证码是否正确
+    ///
+    /// Verify the Captcha code, and return whether user's code is correct.
+    pub fn ver(&self, code: &str) -> bool {
+        match &self.answer {
+            Some(ans) => ans.eq_ignore_ascii_case(code),
+            None => false,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CaptchaAnswer {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let sealed = req
+            .cookies()
+            .get_private(CAPTCHA_KEY)
+            .and_then(|cookie| SealedAnswer::parse(cookie.value()));
+        req.cookies().remove_private(CAPTCHA_KEY);
+
+        let answer = match sealed {
+            Some(sealed) => match req.rocket().state::<CaptchaReplayGuard>() {
+                Some(guard) => sealed.open(&*guard.0).await,
+                None => sealed.open(&*DEFAULT_GUARD).await,
+            },
+            None => None,
+        };
+        Outcome::Success(CaptchaAnswer { answer })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::gif::GifCaptcha;
+    use crate::token::MemoryReplayGuard;
+    use crate::NewCaptcha;
+    use rocket::local::blocking::Client;
+    use rocket::{get, routes};
+
+    #[get("/captcha")]
+    fn get_captcha() -> CaptchaUtil<GifCaptcha> {
+        CaptchaUtil::with_seed(42)
+    }
+
+    #[get("/verify?<code>")]
+    fn verify_captcha(captcha: CaptchaAnswer, code: &str) -> Status {
+        if captcha.ver(code) {
+            Status::Ok
+        } else {
+            Status::Forbidden
+        }
+    }
+
+    #[test]
+    fn it_verifies_codes_in_private_cookies() {
+        let rocket = rocket::build().mount("/", routes![get_captcha, verify_captcha]);
+        let client = Client::tracked(rocket).unwrap();
+
+        let resp = client.get("/captcha").dispatch();
+        assert_eq!(resp.content_type(), Some(ContentType::GIF));
+        let cookie = resp.cookies().get(CAPTCHA_KEY).unwrap();
+        assert!(!cookie.value().contains("WR7BA"));
+
+        // 错误的验证码同样会用掉答案
+        assert_eq!(
+            client.get("/verify?code=wrong").dispatch().status(),
+            Status::Forbidden
+        );
+        assert_eq!(
+            client.get("/verify?code=wr7ba").dispatch().status(),
+            Status::Forbidden
+        );
+
+        // 请求守卫取出答案后会清除Cookie
+        client.get("/captcha").dispatch();
+        assert_eq!(
+            client.get("/verify?code=wr7ba").dispatch().status(),
+            Status::Ok
+        );
+        assert_eq!(
+            client.get("/verify?code=wr7ba").dispatch().status(),
+            Status::Forbidden
+        );
+    }
+
+    #[test]
+    fn it_rejects_resent_cookies() {
+        let rocket = rocket::build()
+            .mount("/", routes![get_captcha, verify_captcha])
+            .manage(CaptchaReplayGuard::new(MemoryReplayGuard::new()));
+        let client = Client::untracked(rocket).unwrap();
+
+        let resp = client.get("/captcha").dispatch();
+        let cookie = resp.cookies().get(CAPTCHA_KEY).unwrap().clone();
+
+        // 截获的Cookie只能使用一次
+        let verify = || client.get("/verify?code=wr7ba").cookie(cookie.clone());
+        assert_eq!(verify().dispatch().status(), Status::Ok);
+        assert_eq!(verify().dispatch().status(), Status::Forbidden);
+
+        // 新的验证码使用新的随机数
+        let resp = client.get("/captcha").dispatch();
+        let cookie = resp.cookies().get(CAPTCHA_KEY).unwrap().clone();
+        assert_eq!(
+            client
+                .get("/verify?code=wr7ba")
+                .cookie(cookie)
+                .dispatch()
+                .status(),
+            Status::Ok
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! 保存在客户端的答案
+//!
+//! Cookie（以及把Session保存在Cookie中的后端）存放在客户端，服务端清除答案后，客户端仍然可以重新提交清除前的旧Cookie。
+//! 因此答案与过期时间、随机数一起保存，验证时通过[ReplayGuard]将随机数标记为已使用，重新提交的旧Cookie会被拒绝。
+
+use crate::token::{MemoryReplayGuard, ReplayGuard};
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use lazy_static::lazy_static;
+use rand::RngCore;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 答案的有效期
+pub(crate) const ANSWER_TTL: Duration = Duration::from_secs(300);
+
+lazy_static! {
+    /// 未指定防重放检查时使用的进程内防重放检查
+    pub(crate) static ref DEFAULT_GUARD: MemoryReplayGuard = MemoryReplayGuard::new();
+}
+
+/// 当前的Unix时间戳
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 带过期时间和随机数的答案，以`过期时间:随机数:答案`的形式保存
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SealedAnswer {
+    expires_at: u64,
+    nonce: String,
+    answer: String,
+}
+
+impl SealedAnswer {
+    /// 为答案生成新的随机数，答案在[ANSWER_TTL]后过期
+    pub(crate) fn new(answer: String) -> Self {
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        Self {
+            expires_at: now() + ANSWER_TTL.as_secs(),
+            nonce: BASE64_URL_SAFE_NO_PAD.encode(nonce),
+            answer,
+        }
+    }
+
+    /// 解析保存的值，格式错误时返回`None`
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, ':');
+        Some(Self {
+            expires_at: parts.next()?.parse().ok()?,
+            nonce: parts.next()?.into(),
+            answer: parts.next()?.into(),
+        })
+    }
+
+    /// 将随机数标记为已使用并取出答案，答案已过期或随机数已被使用过时返回`None`
+    pub(crate) async fn open(self, guard: &dyn ReplayGuard) -> Option<String> {
+        if self.expires_at < now() {
+            return None;
+        }
+        let expires_at = UNIX_EPOCH + Duration::from_secs(self.expires_at);
+        if guard.mark_used(&self.nonce, expires_at).await {
+            Some(self.answer)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for SealedAnswer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.expires_at, self.nonce, self.answer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_opens_each_answer_once() {
+        let guard = MemoryReplayGuard::new();
+        let sealed = SealedAnswer::new("WR7BA".into());
+        let value = sealed.to_string();
+        assert_eq!(SealedAnswer::parse(&value), Some(sealed));
+
+        let open = || SealedAnswer::parse(&value).unwrap().open(&guard);
+        assert_eq!(open().await, Some("WR7BA".into()));
+        assert_eq!(open().await, None);
+
+        // 答案中可以包含冒号
+        let value = SealedAnswer::new("1:2".into()).to_string();
+        assert_eq!(
+            SealedAnswer::parse(&value).unwrap().open(&guard).await,
+            Some("1:2".into())
+        );
+
+        let expired = SealedAnswer {
+            expires_at: now() - 1,
+            ..SealedAnswer::new("WR7BA".into())
+        };
+        assert_eq!(expired.open(&guard).await, None);
+        assert_eq!(SealedAnswer::parse("garbage"), None);
+    }
+}
@@ -0,0 +1,583 @@
+//! Tower 中间件
+//!
+//! - Tower: [tower](https://docs.rs/tower)
+//!
+//! [CaptchaLayer]不依赖于具体的Web框架，可以保护任何基于`http` 1.x的Tower服务，例如axum、hyper和tonic的服务。
+//!
+//! [CaptchaLayer] is not tied to any web framework, and can guard any Tower service based on `http` 1.x, e.g. the
+//! services of axum, hyper and tonic.
+
+use crate::extension::CaptchaUtil;
+use crate::store::{self, CaptchaStore};
+use crate::{AbstractCaptcha, Error, NewCaptcha};
+use bytes::Bytes;
+use http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use http::{Request, Response, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use log::error;
+use pin_project_lite::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// 请求中验证码或验证码编号所在的位置
+///
+/// The location of the code or the Captcha id in the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeLocation {
+    /// 请求头
+    ///
+    /// A request header.
+    Header(HeaderName),
+
+    /// 查询参数
+    ///
+    /// A query parameter.
+    Query(String),
+}
+
+impl CodeLocation {
+    fn extract<B>(&self, req: &Request<B>) -> Option<String> {
+        match self {
+            CodeLocation::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            CodeLocation::Query(name) => form_urlencoded::parse(req.uri().query()?.as_bytes())
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned()),
+        }
+    }
+}
+
+/// 渲染好的验证码：图像、MIME类型和答案
+type Rendered = (Vec<u8>, String, String);
+
+fn render<T: AbstractCaptcha>() -> Result<Rendered, Error> {
+    let mut captcha: CaptchaUtil<T> = CaptchaUtil::new();
+    let mut image = Vec::new();
+    captcha.captcha_instance.out(&mut image)?;
+    let answer = captcha.captcha_instance.get_chars()?.iter().collect();
+    Ok((image, captcha.captcha_instance.get_content_type(), answer))
+}
+
+#[derive(Clone)]
+enum Backend {
+    /// 从`tower-sessions`的Session中读取答案，`key`为答案在Session中的键
+    #[cfg(feature = "axum-tower-sessions")]
+    Session { key: String },
+
+    /// 凭验证码编号从存储中读取答案
+    Store {
+        store: Arc<dyn CaptchaStore>,
+        id: CodeLocation,
+    },
+}
+
+/// 只放行提交了正确验证码的请求的Tower中间件 A Tower layer that only lets through requests carrying the correct code
+///
+/// 验证码默认从请求头`x-captcha-code`中读取，可以通过[with_code](CaptchaLayer::with_code)修改。验证后答案即被清除，每个验证码只能使用一次。
+/// 验证码缺失或不正确时响应`403 Forbidden`；若通过[with_challenge](CaptchaLayer::with_challenge)设置了验证码类型，则同时在响应中返回一张新的验证码。
+///
+/// The code is read from the `x-captcha-code` header by default, which can be changed by
+/// [with_code](CaptchaLayer::with_code). The answer is cleared once checked, so each Captcha can only be used once.
+/// Requests with a missing or wrong code get `403 Forbidden`; if a Captcha type is set by
+/// [with_challenge](CaptchaLayer::with_challenge), a new Captcha is returned in the response as well.
+///
+/// ```no_run
+/// use easy_captcha::captcha::spec::SpecCaptcha;
+/// use easy_captcha::extension::tower::CaptchaLayer;
+/// use easy_captcha::store::MemoryStore;
+/// use std::sync::Arc;
+///
+/// let layer = CaptchaLayer::store(Arc::new(MemoryStore::new())).with_challenge::<SpecCaptcha>();
+/// // 将layer添加到需要保护的服务上，例如`Router::route_layer(layer)`
+/// // Add the layer to the guarded service, e.g. `Router::route_layer(layer)`
+/// ```
+#[derive(Clone)]
+pub struct CaptchaLayer {
+    backend: Backend,
+    code: CodeLocation,
+    challenge: Option<fn() -> Result<Rendered, Error>>,
+    ttl: Duration,
+}
+
+impl CaptchaLayer {
+    fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            code: CodeLocation::Header(HeaderName::from_static("x-captcha-code")),
+            challenge: None,
+            ttl: Duration::from_secs(300),
+        }
+    }
+
+    /// 与Session中保存的验证码比对，需要在外层添加`tower-sessions`的`SessionManagerLayer`
+    ///
+    /// tower-sessions为每个请求单独加载Session，携带同一Session的并发请求仍可能同时通过验证，如需严格防止，请使用
+    /// [store](CaptchaLayer::store)。
+    ///
+    /// Check the code against the Captcha saved in the session; `SessionManagerLayer` of `tower-sessions` must be added
+    /// outside this layer.
+    ///
+    /// tower-sessions loads the session for each request separately, so concurrent requests carrying the same session
+    /// may still pass together; use [store](CaptchaLayer::store) to rule that out strictly.
+    #[cfg(feature = "axum-tower-sessions")]
+    pub fn session() -> Self {
+        Self::new(Backend::Session {
+            key: crate::extension::CAPTCHA_KEY.into(),
+        })
+    }
+
+    /// 与Session中指定命名空间的验证码比对，参见[session](CaptchaLayer::session)和
+    /// [out_for](crate::extension::axum_tower_sessions::CaptchaAxumTowerSessionExt::out_for)
+    ///
+    /// Check the code against the Captcha of the given namespace in the session; see [session](CaptchaLayer::session)
+    /// and [out_for](crate::extension::axum_tower_sessions::CaptchaAxumTowerSessionExt::out_for).
+    #[cfg(feature = "axum-tower-sessions")]
+    pub fn session_for(namespace: &str) -> Self {
+        Self::new(Backend::Session {
+            key: crate::extension::axum_tower_sessions::namespaced_key(namespace),
+        })
+    }
+
+    /// 凭验证码编号与存储中的答案比对，编号默认从请求头`x-captcha-id`中读取
+    ///
+    /// Check the code against the answer in the storage by the Captcha id, which is read from the `x-captcha-id` header
+    /// by default.
+    pub fn store(store: Arc<dyn CaptchaStore>) -> Self {
+        Self::new(Backend::Store {
+            store,
+            id: CodeLocation::Header(HeaderName::from_static("x-captcha-id")),
+        })
+    }
+
+    /// 设置验证码所在的位置
+    ///
+    /// Set the location of the code.
+    pub fn with_code(mut self, code: CodeLocation) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// 设置验证码编号所在的位置，仅对[store](CaptchaLayer::store)有效
+    ///
+    /// Set the location of the Captcha id; only takes effect with [store](CaptchaLayer::store).
+    pub fn with_id(mut self, location: CodeLocation) -> Self {
+        match &mut self.backend {
+            Backend::Store { id, .. } => *id = location,
+            #[cfg(feature = "axum-tower-sessions")]
+            Backend::Session { .. } => {}
+        }
+        self
+    }
+
+    /// 拒绝请求时返回一张类型为`T`的新验证码。使用存储时，新验证码的编号在响应头`x-captcha-id`中返回
+    ///
+    /// Return a new Captcha of type `T` when rejecting requests. With a storage, the id of the new Captcha is returned
+    /// in the `x-captcha-id` response header.
+    pub fn with_challenge<T: AbstractCaptcha>(mut self) -> Self {
+        self.challenge = Some(render::<T>);
+        self
+    }
+
+    /// 设置存储中新验证码的有效期，默认为5分钟
+    ///
+    /// Set the lifetime of new Captchas in the storage, 5 minutes by default.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+impl<S> Layer<S> for CaptchaLayer {
+    type Service = CaptchaService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CaptchaService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// [CaptchaLayer]生成的服务 The service produced by [CaptchaLayer]
+#[derive(Clone)]
+pub struct CaptchaService<S> {
+    inner: S,
+    layer: CaptchaLayer,
+}
+
+/// 从请求中取出的验证所需信息，避免在等待期间持有请求的引用
+struct Submission {
+    code: Option<String>,
+    #[cfg(feature = "axum-tower-sessions")]
+    session: Option<tower_sessions::Session>,
+    id: Option<String>,
+}
+
+impl CaptchaLayer {
+    fn submission<B>(&self, req: &Request<B>) -> Submission {
+        Submission {
+            code: self.code.extract(req),
+            #[cfg(feature = "axum-tower-sessions")]
+            session: req.extensions().get().cloned(),
+            id: match &self.backend {
+                Backend::Store { id, .. } => id.extract(req),
+                #[cfg(feature = "axum-tower-sessions")]
+                Backend::Session { .. } => None,
+            },
+        }
+    }
+
+    async fn verify(&self, submission: &Submission) -> Result<bool, Error> {
+        let code = match &submission.code {
+            Some(code) => code,
+            None => return Ok(false),
+        };
+
+        match &self.backend {
+            #[cfg(feature = "axum-tower-sessions")]
+            Backend::Session { key } => {
+                // 先取出答案再比较，无论是否正确，答案都会失效
+                let session = submission.session.as_ref().ok_or_else(missing_session)?;
+                let answer = session
+                    .remove::<String>(key)
+                    .await
+                    .map_err(|e| Error::Storage(Box::new(e)))?;
+                Ok(answer.map_or(false, |ans| ans.eq_ignore_ascii_case(code)))
+            }
+            Backend::Store { store, .. } => match &submission.id {
+                Some(id) => CaptchaUtil::verify(store.as_ref(), id, code).await,
+                None => Ok(false),
+            },
+        }
+    }
+
+    #[cfg_attr(not(feature = "axum-tower-sessions"), allow(unused_variables))]
+    async fn reject<B>(&self, submission: &Submission) -> Result<Response<CaptchaBody<B>>, Error> {
+        let mut response = Response::new(CaptchaBody::empty());
+        *response.status_mut() = StatusCode::FORBIDDEN;
+
+        let (image, content_type, answer) = match self.challenge {
+            Some(render) => render()?,
+            None => return Ok(response),
+        };
+
+        match &self.backend {
+            #[cfg(feature = "axum-tower-sessions")]
+            Backend::Session { key } => {
+                let session = submission.session.as_ref().ok_or_else(missing_session)?;
+                session
+                    .insert(key, answer)
+                    .await
+                    .map_err(|e| Error::Storage(Box::new(e)))?;
+            }
+            Backend::Store { store, id } => {
+                let new_id = store::new_id();
+                store.put(&new_id, &answer, self.ttl).await?;
+
+                let name = match id {
+                    CodeLocation::Header(name) => name.clone(),
+                    CodeLocation::Query(_) => HeaderName::from_static("x-captcha-id"),
+                };
+                let value =
+                    HeaderValue::from_str(&new_id).map_err(|e| Error::Storage(Box::new(e)))?;
+                response.headers_mut().insert(name, value);
+            }
+        }
+
+        let content_type =
+            HeaderValue::from_str(&content_type).map_err(|e| Error::Encoding(Box::new(e)))?;
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+        *response.body_mut() = CaptchaBody::full(image);
+        Ok(response)
+    }
+}
+
+#[cfg(feature = "axum-tower-sessions")]
+fn missing_session() -> Error {
+    Error::Storage("Can't extract session. Is `SessionManagerLayer` enabled?".into())
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CaptchaService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = Response<CaptchaBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // 已就绪的是当前的服务，将其取出使用，并留下一个克隆
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+        let submission = layer.submission(&req);
+
+        Box::pin(async move {
+            let result = match layer.verify(&submission).await {
+                Ok(true) => return Ok(inner.call(req).await?.map(CaptchaBody::inner)),
+                Ok(false) => layer.reject(&submission).await,
+                Err(e) => Err(e),
+            };
+
+            Ok(result.unwrap_or_else(|e| {
+                error!("Unable to verify the Captcha: {}", e);
+                let mut response = Response::new(CaptchaBody::empty());
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                response
+            }))
+        })
+    }
+}
+
+pin_project! {
+    /// [CaptchaService]的响应体：内部服务的响应体，或拒绝请求时由中间件生成的响应体
+    ///
+    /// The response body of [CaptchaService]: either the body of the inner service, or the body generated by the layer
+    /// when rejecting the request.
+    pub struct CaptchaBody<B> {
+        #[pin]
+        kind: Kind<B>,
+    }
+}
+
+pin_project! {
+    #[project = KindProj]
+    enum Kind<B> {
+        Inner { #[pin] body: B },
+        Full { data: Option<Bytes> },
+    }
+}
+
+impl<B> CaptchaBody<B> {
+    fn inner(body: B) -> Self {
+        Self {
+            kind: Kind::Inner { body },
+        }
+    }
+
+    fn full(data: Vec<u8>) -> Self {
+        Self {
+            kind: Kind::Full {
+                data: Some(data.into()),
+            },
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            kind: Kind::Full { data: None },
+        }
+    }
+}
+
+impl<B: Body<Data = Bytes>> Body for CaptchaBody<B> {
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match self.project().kind.project() {
+            KindProj::Inner { body } => body.poll_frame(cx),
+            KindProj::Full { data } => Poll::Ready(data.take().map(|data| Ok(Frame::data(data)))),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.kind {
+            Kind::Inner { body } => body.is_end_stream(),
+            Kind::Full { data } => data.is_none(),
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.kind {
+            Kind::Inner { body } => body.size_hint(),
+            Kind::Full { data } => {
+                SizeHint::with_exact(data.as_ref().map_or(0, |d| d.len() as u64))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::spec::SpecCaptcha;
+    use crate::store::MemoryStore;
+    use std::convert::Infallible;
+    use std::fmt::Debug;
+    use tower::{service_fn, ServiceExt};
+
+    async fn body_bytes<B: Body<Data = Bytes> + Unpin>(mut body: B) -> Vec<u8>
+    where
+        B::Error: Debug,
+    {
+        let mut bytes = Vec::new();
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+        {
+            if let Ok(data) = frame.unwrap().into_data() {
+                bytes.extend_from_slice(&data);
+            }
+        }
+        bytes
+    }
+
+    #[tokio::test]
+    async fn it_guards_services_with_a_store() {
+        let store = Arc::new(MemoryStore::new());
+        store
+            .put("id", "abcd", Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let service = CaptchaLayer::store(store.clone())
+            .with_code(CodeLocation::Query("code".into()))
+            .with_challenge::<SpecCaptcha>()
+            .layer(service_fn(|_: Request<String>| async {
+                Ok::<_, Infallible>(Response::new(String::from("protected")))
+            }));
+        let request = |uri: &str| {
+            Request::get(uri)
+                .header("x-captcha-id", "id")
+                .body(String::new())
+                .unwrap()
+        };
+
+        let resp = service
+            .clone()
+            .oneshot(request("/?code=ABCD"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_bytes(resp.into_body()).await, b"protected");
+
+        // 验证码只能使用一次，再次提交时返回新的验证码 Each Captcha can only be used once, and a new one is returned
+        let resp = service.oneshot(request("/?code=abcd")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "image/png");
+        let id = resp.headers()["x-captcha-id"].to_str().unwrap().to_string();
+        assert!(store.get(&id).await.unwrap().is_some());
+        assert!(body_bytes(resp.into_body()).await.starts_with(b"\x89PNG"));
+    }
+
+    #[cfg(feature = "axum-tower-sessions")]
+    #[tokio::test]
+    async fn it_guards_routes_with_the_session() {
+        use crate::extension::axum_tower_sessions::CaptchaAxumTowerSessionExt;
+        use axum::body::Body;
+        use axum::routing::get;
+        use axum::Router;
+        use tower_sessions::{MemoryStore, Session, SessionManagerLayer};
+
+        async fn get_captcha(session: Session) -> Result<axum::response::Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out(&session).await
+        }
+
+        let router = Router::new()
+            .route("/protected", get(|| async { "protected" }))
+            .route_layer(CaptchaLayer::session())
+            .route("/captcha", get(get_captcha))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+        let send = |req| router.clone().oneshot(req);
+
+        let resp = send(Request::get("/captcha").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let cookie = resp.headers()[http::header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+
+        let protected = |code: &str| {
+            Request::get("/protected")
+                .header(http::header::COOKIE, &cookie)
+                .header("x-captcha-code", code)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let resp = send(protected("wr7ba")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = send(protected("wr7ba")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[cfg(feature = "axum-tower-sessions")]
+    #[tokio::test]
+    async fn it_guards_routes_with_a_session_namespace() {
+        use crate::extension::axum_tower_sessions::CaptchaAxumTowerSessionExt;
+        use axum::body::Body;
+        use axum::extract::Path;
+        use axum::routing::get;
+        use axum::Router;
+        use tower_sessions::{MemoryStore, Session, SessionManagerLayer};
+
+        async fn get_captcha(
+            session: Session,
+            Path(namespace): Path<String>,
+        ) -> Result<axum::response::Response, Error> {
+            let mut captcha: CaptchaUtil<SpecCaptcha> = CaptchaUtil::with_seed(42);
+            captcha.out_for(&namespace, &session).await
+        }
+
+        let router = Router::new()
+            .route("/protected", get(|| async { "protected" }))
+            .route_layer(CaptchaLayer::session_for("login"))
+            .route("/captcha/:namespace", get(get_captcha))
+            .layer(SessionManagerLayer::new(MemoryStore::default()));
+        let send = |req| router.clone().oneshot(req);
+
+        let resp = send(Request::get("/captcha/login").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let cookie = resp.headers()[http::header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let request = |uri: &str, code: &str| {
+            Request::get(uri)
+                .header(http::header::COOKIE, &cookie)
+                .header("x-captcha-code", code)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // 两个请求携带同一Cookie，第一次提交错误的验证码后答案即失效
+        let resp = send(request("/protected", "wrong")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = send(request("/protected", "wr7ba")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // 其他命名空间的验证码不会通过验证
+        send(request("/captcha/comment", "")).await.unwrap();
+        let resp = send(request("/protected", "wr7ba")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        send(request("/captcha/login", "")).await.unwrap();
+        let resp = send(request("/protected", "wr7ba")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -8,6 +8,10 @@
 //! 目前已适配框架 / Frameworks which is adapted now:
 //!
 //!  - `axum` + `tower-sessions`
+//!  - `actix-web` + `actix-session`
+//!  - `rocket`（私有Cookie / private cookies）
+//!  - `poem`（Session中间件 / session middleware）
+//!  - `tower`（通用中间件，可用于hyper、tonic等 / a generic layer for hyper, tonic, etc.）
 //!
 //!  更多框架欢迎您提交PR，参与适配🙏 PR for new frameworks are welcomed
 //!
@@ -88,12 +92,16 @@
 //! }
 //! ```
 //!
-//! 项目当前提供了三种验证码实现：[SpecCaptcha](captcha::spec::SpecCaptcha)（静态PNG）、[GifCaptcha](captcha::gif::GifCaptcha)（动态GIF）
-//! 、[ArithmeticCaptcha](captcha::arithmetic::ArithmeticCaptcha)（算术PNG），您可按需使用。
+//! 项目当前提供了六种验证码实现：[SpecCaptcha](captcha::spec::SpecCaptcha)（静态PNG）、[GifCaptcha](captcha::gif::GifCaptcha)（动态GIF）
+//! 、[ArithmeticCaptcha](captcha::arithmetic::ArithmeticCaptcha)（算术PNG）、[ArithmeticGifCaptcha](captcha::arithmetic_gif::ArithmeticGifCaptcha)（算术GIF）
+//! 、[ChineseCaptcha](captcha::chinese::ChineseCaptcha)（中文PNG）、[ChineseGifCaptcha](captcha::chinese_gif::ChineseGifCaptcha)（中文GIF），
+//! 您可按需使用。
 //!
-//! There is three implementation of Captcha currently, which are [SpecCaptcha](captcha::spec::SpecCaptcha)(static PNG),
+//! There is six implementation of Captcha currently, which are [SpecCaptcha](captcha::spec::SpecCaptcha)(static PNG),
 //! [GifCaptcha](captcha::gif::GifCaptcha)(GIF), [ArithmeticCaptcha](captcha::arithmetic::ArithmeticCaptcha)(Arithmetic problems),
-//! you can use them according to your need.
+//! [ArithmeticGifCaptcha](captcha::arithmetic_gif::ArithmeticGifCaptcha)(Arithmetic problems GIF),
+//! [ChineseCaptcha](captcha::chinese::ChineseCaptcha)(Chinese characters),
+//! [ChineseGifCaptcha](captcha::chinese_gif::ChineseGifCaptcha)(Chinese characters GIF), you can use them according to your need.
 //!
 //! <br/>
 //!
@@ -112,6 +120,16 @@
 //! | CaptchaFont::Font9  | ![](https://s2.ax1x.com/2019/08/23/msMVpV.png) |
 //! | CaptchaFont::Font10 | ![](https://s2.ax1x.com/2019/08/23/msMZlT.png) |
 //!
+//! 除内置字体外，您也可以通过[FontSource]使用字体文件、字体数据或系统字体，并让每个字符轮换使用多个字体。
+//!
+//! Besides the built-in fonts, you may also use font files, font data or system fonts by [FontSource], and let the
+//! characters rotate among several fonts.
+//!
+//! 若服务是无状态的，可以使用[token]模块签发带签名的令牌，无需在服务端保存验证码。
+//!
+//! For stateless services, the [token] module issues signed tokens, so that the Captcha needn't be stored on the
+//! server.
+//!
 
 #![warn(missing_docs)]
 #![allow(dead_code)]
@@ -119,9 +137,19 @@
 pub(crate) mod base;
 pub mod captcha;
 pub mod extension;
+pub mod store;
+pub mod token;
 mod utils;
 
+pub use base::background::Background;
 pub use base::captcha::*;
+pub use base::charset::Charset;
+pub use base::error::Error;
+pub use base::font::{FontError, FontSource};
+pub use base::layout::GlyphLayout;
+pub use base::noise::Noise;
+pub use base::transform::GlyphTransform;
+pub use base::warp::Warp;
 
 // #[cfg(test)]
 // mod tests {
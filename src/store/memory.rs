@@ -0,0 +1,159 @@
+use crate::store::CaptchaStore;
+use crate::Error;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 进程内的验证码存储，只适用于单实例部署
+///
+/// 过期的答案不会被返回，并会在写入时定期清除。
+///
+/// In-process storage of the Captcha, which only suits single-instance deployments.
+///
+/// Expired answers are never returned, and are swept periodically on writes.
+#[derive(Debug)]
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+
+    /// 清除过期答案的间隔
+    sweep_interval: Duration,
+}
+
+#[derive(Debug)]
+struct Inner {
+    answers: HashMap<String, (String, Instant)>,
+
+    /// 下次清除过期答案的时间
+    next_sweep: Instant,
+}
+
+impl MemoryStore {
+    /// 创建空的存储，每分钟清除一次过期答案
+    ///
+    /// Create an empty storage, which sweeps expired answers every minute.
+    pub fn new() -> Self {
+        Self::with_sweep_interval(Duration::from_secs(60))
+    }
+
+    /// 创建空的存储，并指定清除过期答案的间隔
+    ///
+    /// Create an empty storage with the interval of sweeping expired answers.
+    pub fn with_sweep_interval(sweep_interval: Duration) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                answers: HashMap::new(),
+                next_sweep: Instant::now() + sweep_interval,
+            }),
+            sweep_interval,
+        }
+    }
+
+    /// 立即清除所有过期的答案
+    ///
+    /// Sweep all the expired answers immediately.
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+        inner.answers.retain(|_, (_, expires_at)| *expires_at > now);
+        inner.next_sweep = now + self.sweep_interval;
+    }
+
+    /// 当前保存的答案数量，包括尚未清除的过期答案
+    ///
+    /// The count of saved answers, including the expired ones which are not swept yet.
+    pub fn len(&self) -> usize {
+        self.inner.lock().answers.len()
+    }
+
+    /// 是否没有保存任何答案
+    ///
+    /// Whether no answer is saved.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CaptchaStore for MemoryStore {
+    async fn put(&self, id: &str, answer: &str, ttl: Duration) -> Result<(), Error> {
+        let now = Instant::now();
+        let expires_at = now
+            .checked_add(ttl)
+            .ok_or_else(|| Error::InvalidConfig(format!("The TTL {:?} is too long", ttl)))?;
+        if self.inner.lock().next_sweep <= now {
+            self.sweep();
+        }
+
+        self.inner
+            .lock()
+            .answers
+            .insert(id.into(), (answer.into(), expires_at));
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<String>, Error> {
+        let inner = self.inner.lock();
+        Ok(match inner.answers.get(id) {
+            Some((answer, expires_at)) if *expires_at > Instant::now() => Some(answer.clone()),
+            _ => None,
+        })
+    }
+
+    async fn take(&self, id: &str) -> Result<Option<String>, Error> {
+        let mut inner = self.inner.lock();
+        Ok(match inner.answers.remove(id) {
+            Some((answer, expires_at)) if expires_at > Instant::now() => Some(answer),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_stores_and_takes_answers() {
+        let store = MemoryStore::new();
+        store
+            .put("id", "abcd", Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert_eq!(store.get("id").await.unwrap(), Some("abcd".into()));
+        assert_eq!(store.take("id").await.unwrap(), Some("abcd".into()));
+        assert_eq!(store.take("id").await.unwrap(), None);
+        assert_eq!(store.get("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn it_expires_and_sweeps_answers() {
+        let store = MemoryStore::with_sweep_interval(Duration::ZERO);
+        store.put("old", "abcd", Duration::ZERO).await.unwrap();
+        assert_eq!(store.get("old").await.unwrap(), None);
+
+        store
+            .put("new", "efgh", Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.take("new").await.unwrap(), Some("efgh".into()));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_ttl_beyond_the_clock() {
+        let store = MemoryStore::new();
+        assert!(matches!(
+            store.put("id", "abcd", Duration::MAX).await,
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+//! 验证码的存储 Storages of the Captcha
+//!
+//! 签发验证码时会生成一个随机的验证码编号，答案以该编号为键保存在[CaptchaStore]中，验证时凭编号取出答案，不依赖任何Web框架或Session。
+//!
+//! When issuing a Captcha, a random Captcha id is generated and the answer is saved in a [CaptchaStore] by the id.
+//! To verify, the answer is taken out by the id, which doesn't depend on any web framework or session.
+
+mod memory;
+#[cfg(feature = "redis-store")]
+mod redis;
+
+#[cfg(feature = "redis-store")]
+pub use self::redis::RedisStore;
+pub use memory::MemoryStore;
+
+use crate::Error;
+use async_trait::async_trait;
+use base64::prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD};
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 签发的验证码 An issued Captcha
+#[derive(Debug, Clone)]
+pub struct IssuedCaptcha {
+    /// 验证码编号，验证时凭编号取出答案
+    ///
+    /// The Captcha id, by which the answer is taken out for verification.
+    pub id: String,
+
+    /// 验证码图像
+    ///
+    /// The Captcha image.
+    pub image: Vec<u8>,
+
+    /// 验证码图像的MIME类型
+    ///
+    /// The MIME Content type of the image.
+    pub content_type: String,
+
+    /// 验证码的过期时间
+    ///
+    /// The expiry time of the Captcha.
+    pub expires_at: SystemTime,
+}
+
+/// 以JSON返回的验证码 A Captcha returned as JSON
+///
+/// 适用于通过`fetch`等方式请求验证码的单页应用，客户端提交验证码时需要同时提交`id`。
+///
+/// Suitable for single-page applications requesting the Captcha via `fetch` and the like; the client should submit the
+/// `id` along with the code.
+///
+/// ```json
+/// {"id": "...", "image": "data:image/png;base64,...", "mime": "image/png", "expires_at": 1700000000}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptchaJson {
+    /// 验证码编号
+    ///
+    /// The Captcha id.
+    pub id: String,
+
+    /// 带编码头的Base64图像（Data URI），可以直接用作`<img>`的`src`
+    ///
+    /// The Base64 encoded image with the encoding head (a data URI), which can be used as the `src` of `<img>` directly.
+    pub image: String,
+
+    /// 验证码图像的MIME类型
+    ///
+    /// The MIME Content type of the image.
+    pub mime: String,
+
+    /// 验证码的过期时间，单位为秒的Unix时间戳
+    ///
+    /// The expiry time of the Captcha, as a Unix timestamp in seconds.
+    pub expires_at: u64,
+}
+
+impl From<IssuedCaptcha> for CaptchaJson {
+    fn from(value: IssuedCaptcha) -> Self {
+        Self {
+            image: format!(
+                "data:{};base64,{}",
+                value.content_type,
+                BASE64_STANDARD.encode(&value.image)
+            ),
+            id: value.id,
+            mime: value.content_type,
+            expires_at: value
+                .expires_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+/// 验证码存储 Storage of the Captcha answers
+///
+/// 实现者需要在过期后丢弃答案。
+///
+/// Implementors should discard the answers after they expire.
+#[async_trait]
+pub trait CaptchaStore: Send + Sync {
+    /// 保存验证码答案，在`ttl`后过期
+    ///
+    /// Save the answer of a Captcha, which expires after `ttl`.
+    async fn put(&self, id: &str, answer: &str, ttl: Duration) -> Result<(), Error>;
+
+    /// 获取验证码答案，答案不存在或已过期时返回`None`
+    ///
+    /// Get the answer of a Captcha; return `None` if it doesn't exist or has expired.
+    async fn get(&self, id: &str) -> Result<Option<String>, Error>;
+
+    /// 取出并删除验证码答案，答案不存在或已过期时返回`None`
+    ///
+    /// Take out and remove the answer of a Captcha; return `None` if it doesn't exist or has expired.
+    async fn take(&self, id: &str) -> Result<Option<String>, Error>;
+}
+
+/// 计算从现在起经过`ttl`后的过期时间，`ttl`过大而无法表示时返回[InvalidConfig](Error::InvalidConfig)
+pub(crate) fn expires_at(ttl: Duration) -> Result<SystemTime, Error> {
+    SystemTime::now()
+        .checked_add(ttl)
+        .ok_or_else(|| Error::InvalidConfig(format!("The TTL {:?} is too long", ttl)))
+}
+
+/// 生成随机的验证码编号
+pub(crate) fn new_id() -> String {
+    let mut id = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut id);
+    BASE64_URL_SAFE_NO_PAD.encode(id)
+}
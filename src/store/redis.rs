@@ -0,0 +1,211 @@
+use crate::store::CaptchaStore;
+use crate::Error;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use std::time::Duration;
+
+/// 基于Redis的验证码存储，适用于多实例部署
+///
+/// 答案通过`SET ... EX`写入，验证时通过`GETDEL`原子地取出，同一个答案只能被一个实例取出一次。需要Redis 6.2或更高版本。
+///
+/// Redis-backed storage of the Captcha, which suits multi-instance deployments.
+///
+/// Answers are written by `SET ... EX`, and atomically taken out by `GETDEL` on verification, so an answer can be
+/// taken out only once by one of the instances. Redis 6.2 or higher is required.
+///
+/// ```no_run
+/// use easy_captcha::extension::CaptchaUtil;
+/// use easy_captcha::store::RedisStore;
+/// use easy_captcha::NewCaptcha;
+/// use std::time::Duration;
+///
+/// # async fn run() -> Result<(), easy_captcha::Error> {
+/// let store = RedisStore::connect("redis://127.0.0.1/").await?;
+/// let mut captcha: CaptchaUtil = CaptchaUtil::new();
+/// let issued = captcha.issue(&store, Duration::from_secs(300)).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RedisStore {
+    conn: ConnectionManager,
+
+    /// 键的前缀
+    prefix: String,
+}
+
+impl RedisStore {
+    /// 使用Redis连接创建，键的前缀默认为`ez-captcha:`
+    ///
+    /// Create with the Redis connection; the prefix of keys is `ez-captcha:` by default.
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            prefix: "ez-captcha:".into(),
+        }
+    }
+
+    /// 连接到指定地址的Redis，例如`redis://127.0.0.1/`
+    ///
+    /// Connect to the Redis at the specified URL, e.g. `redis://127.0.0.1/`.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let client = redis::Client::open(url).map_err(storage_error)?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .map_err(storage_error)?;
+        Ok(Self::new(conn))
+    }
+
+    /// 设置键的前缀
+    ///
+    /// Set the prefix of keys.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, id: &str) -> String {
+        format!("{}{}", self.prefix, id)
+    }
+}
+
+fn storage_error(e: redis::RedisError) -> Error {
+    Error::Storage(Box::new(e))
+}
+
+#[async_trait]
+impl CaptchaStore for RedisStore {
+    async fn put(&self, id: &str, answer: &str, ttl: Duration) -> Result<(), Error> {
+        // EX的单位为秒且必须为正数，不足一秒的部分向上取整
+        let seconds = (ttl.as_secs_f64().ceil() as u64).max(1);
+        redis::cmd("SET")
+            .arg(self.key(id))
+            .arg(answer)
+            .arg("EX")
+            .arg(seconds)
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<String>, Error> {
+        redis::cmd("GET")
+            .arg(self.key(id))
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn take(&self, id: &str) -> Result<Option<String>, Error> {
+        redis::cmd("GETDEL")
+            .arg(self.key(id))
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(storage_error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extension::CaptchaUtil;
+    use crate::NewCaptcha;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    type Commands = Arc<Mutex<Vec<Vec<String>>>>;
+
+    /// 进程内的RESP服务，只实现了存储用到的命令，并记录收到的命令
+    async fn serve_resp() -> (String, Commands) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}/", listener.local_addr().unwrap());
+        let commands = Commands::default();
+        let data = Arc::new(Mutex::new(HashMap::<String, String>::new()));
+
+        let log = commands.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle(stream, data.clone(), log.clone()));
+            }
+        });
+
+        (url, commands)
+    }
+
+    async fn handle(stream: TcpStream, data: Arc<Mutex<HashMap<String, String>>>, log: Commands) {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        while let Some(args) = read_command(&mut reader).await {
+            log.lock().unwrap().push(args.clone());
+            let bulk = |value: Option<String>| match value {
+                Some(v) => format!("${}\r\n{}\r\n", v.len(), v),
+                None => "$-1\r\n".into(),
+            };
+
+            let reply: String = {
+                let mut data = data.lock().unwrap();
+                match args[0].to_ascii_uppercase().as_str() {
+                    "CLIENT" => "+OK\r\n".into(),
+                    "SET" => {
+                        data.insert(args[1].clone(), args[2].clone());
+                        "+OK\r\n".into()
+                    }
+                    "GET" => bulk(data.get(&args[1]).cloned()),
+                    "GETDEL" => bulk(data.remove(&args[1])),
+                    _ => "-ERR unknown command\r\n".into(),
+                }
+            };
+            writer.write_all(reply.as_bytes()).await.unwrap();
+        }
+    }
+
+    async fn read_command(
+        reader: &mut BufReader<impl AsyncReadExt + Unpin>,
+    ) -> Option<Vec<String>> {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let count: usize = line.trim().strip_prefix('*')?.parse().ok()?;
+
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+            let len: usize = line.trim().strip_prefix('$')?.parse().ok()?;
+            let mut arg = vec![0u8; len + 2];
+            reader.read_exact(&mut arg).await.ok()?;
+            arg.truncate(len);
+            args.push(String::from_utf8(arg).ok()?);
+        }
+        Some(args)
+    }
+
+    #[tokio::test]
+    async fn it_stores_answers_in_redis() {
+        let (url, commands) = serve_resp().await;
+        let store = RedisStore::connect(&url).await.unwrap();
+
+        let mut captcha: CaptchaUtil = CaptchaUtil::with_seed(42);
+        let issued = captcha
+            .issue(&store, Duration::from_millis(1500))
+            .await
+            .unwrap();
+        assert_eq!(store.get(&issued.id).await.unwrap(), Some("WR7BA".into()));
+        assert!(CaptchaUtil::verify(&store, &issued.id, "wr7ba")
+            .await
+            .unwrap());
+        assert!(!CaptchaUtil::verify(&store, &issued.id, "wr7ba")
+            .await
+            .unwrap());
+
+        let key = format!("ez-captcha:{}", issued.id);
+        let commands = commands.lock().unwrap();
+        let commands: Vec<_> = commands.iter().filter(|c| c[0] != "CLIENT").collect();
+        assert_eq!(commands[0], &["SET", &key, "WR7BA", "EX", "2"]);
+        assert_eq!(commands[1], &["GET", &key]);
+        assert_eq!(commands[2], &["GETDEL", &key]);
+        assert_eq!(commands[3], &["GETDEL", &key]);
+    }
+}
@@ -0,0 +1,394 @@
+//! 无状态的签名验证码令牌 Stateless signed Captcha tokens
+//!
+//! 签发验证码时，答案不会保存在服务端，而是与随机数、过期时间一起用HMAC-SHA256签名生成令牌，随图片一起返回给客户端；
+//! 验证时客户端提交令牌和用户输入的验证码，服务端只需密钥即可完成验证，适用于负载均衡后的无状态服务。
+//!
+//! When issuing a Captcha, the answer is not stored on the server. Instead, it is signed with HMAC-SHA256 together
+//! with a nonce and the expiry time into a token, which is returned to the client with the image. To verify, the
+//! client submits the token and the user's code, and the server only needs the key to check them, which fits
+//! stateless services behind a load balancer.
+//!
+//! 令牌中不包含答案本身，答案只参与签名，因此无法从令牌中离线破解答案。令牌在有效期内可以被重复提交，如需保证每个令牌只能使用一次，
+//! 请使用[verify_once](TokenSigner::verify_once)并提供一个[ReplayGuard]。
+//!
+//! The token doesn't contain the answer itself; the answer only takes part in the signature, so it can't be cracked
+//! offline from the token. A token may be submitted again and again before it expires; to make sure every token is
+//! used only once, use [verify_once](TokenSigner::verify_once) with a [ReplayGuard].
+//!
+//! ```
+//! use easy_captcha::captcha::spec::SpecCaptcha;
+//! use easy_captcha::token::TokenSigner;
+//! use easy_captcha::{AbstractCaptcha, NewCaptcha};
+//!
+//! let signer = TokenSigner::new(b"a secret key of at least 32 bytes!!");
+//!
+//! let mut captcha = SpecCaptcha::new();
+//! let signed = signer.issue(&mut captcha).unwrap();
+//! // 将signed.image和signed.token返回给客户端 Return signed.image and signed.token to the client
+//!
+//! let code: String = captcha.get_chars().unwrap().iter().collect();
+//! assert!(signer.verify(&signed.token, &code).is_ok());
+//! assert!(signer.verify(&signed.token, "wrong").is_err());
+//! ```
+
+use crate::{AbstractCaptcha, Error};
+use async_trait::async_trait;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use rand::RngCore;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 随机数的字节数
+const NONCE_LEN: usize = 16;
+
+/// 过期时间的字节数
+const EXPIRY_LEN: usize = 8;
+
+/// 截断后的签名字节数
+const TAG_LEN: usize = 16;
+
+/// 令牌的字节数：随机数、过期时间、令牌签名、答案签名
+const TOKEN_LEN: usize = NONCE_LEN + EXPIRY_LEN + TAG_LEN + TAG_LEN;
+
+/// 验证令牌失败的原因 Reasons why a token fails the verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    /// 令牌格式错误或签名无效
+    ///
+    /// The token is malformed or its signature is invalid.
+    Malformed,
+
+    /// 令牌已过期
+    ///
+    /// The token has expired.
+    Expired,
+
+    /// 令牌已被使用过
+    ///
+    /// The token has been used.
+    Replayed,
+
+    /// 验证码不正确
+    ///
+    /// The code is incorrect.
+    Mismatch,
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Malformed => write!(f, "The Captcha token is malformed."),
+            TokenError::Expired => write!(f, "The Captcha token has expired."),
+            TokenError::Replayed => write!(f, "The Captcha token has been used."),
+            TokenError::Mismatch => write!(f, "The Captcha code is incorrect."),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+/// 签发的验证码 An issued Captcha
+#[derive(Debug, Clone)]
+pub struct SignedCaptcha {
+    /// 验证码图像
+    ///
+    /// The Captcha image.
+    pub image: Vec<u8>,
+
+    /// 验证码图像的MIME类型
+    ///
+    /// The MIME Content type of the image.
+    pub content_type: String,
+
+    /// 签名令牌
+    ///
+    /// The signed token.
+    pub token: String,
+
+    /// 令牌的过期时间
+    ///
+    /// The expiry time of the token.
+    pub expires_at: SystemTime,
+}
+
+/// 防重放检查 Replay protection
+///
+/// 每个令牌都有唯一的随机数，实现者需要记录已使用过的随机数，直到令牌过期。可以使用Redis等共享存储来实现多实例间的防重放。
+///
+/// Every token has a unique nonce; implementors should remember the used nonces until the tokens expire. A shared
+/// storage like Redis may be used to protect against replays across instances.
+#[async_trait]
+pub trait ReplayGuard: Send + Sync {
+    /// 标记随机数已被使用，随机数首次使用时返回`true`，已被使用过时返回`false`
+    ///
+    /// Mark the nonce as used; return `true` if it is used for the first time, and `false` if it has been used.
+    async fn mark_used(&self, nonce: &str, expires_at: SystemTime) -> bool;
+}
+
+/// 进程内的防重放检查，只适用于单实例部署
+///
+/// In-process replay protection, which only suits single-instance deployments.
+#[derive(Debug, Default)]
+pub struct MemoryReplayGuard {
+    used: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl MemoryReplayGuard {
+    /// 创建空的防重放检查
+    ///
+    /// Create an empty replay guard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ReplayGuard for MemoryReplayGuard {
+    async fn mark_used(&self, nonce: &str, expires_at: SystemTime) -> bool {
+        let now = SystemTime::now();
+        let mut used = self.used.lock();
+        // 清除已过期的记录，过期的令牌本身就无法通过验证
+        used.retain(|_, expires_at| *expires_at >= now);
+        used.insert(nonce.into(), expires_at).is_none()
+    }
+}
+
+/// 令牌的签发和验证 Issuing and verifying tokens
+#[derive(Clone)]
+pub struct TokenSigner {
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl TokenSigner {
+    /// 使用密钥创建，令牌的有效期默认为5分钟。密钥应为至少32字节的随机数据，且在所有实例间共享。
+    ///
+    /// Create with the key; tokens are valid for 5 minutes by default. The key should be at least 32 bytes of random
+    /// data, and be shared among all the instances.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+            ttl: Duration::from_secs(300),
+        }
+    }
+
+    /// 设置令牌的有效期
+    ///
+    /// Set the valid period of tokens.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 输出验证码图像，并签发对应的令牌
+    ///
+    /// Write the Captcha image, and issue the corresponding token.
+    pub fn issue(&self, captcha: &mut impl AbstractCaptcha) -> Result<SignedCaptcha, Error> {
+        let mut image = Vec::new();
+        captcha.out(&mut image)?;
+        let answer: String = captcha.get_chars()?.iter().collect();
+        let expires_at = SystemTime::now() + self.ttl;
+
+        Ok(SignedCaptcha {
+            image,
+            content_type: captcha.get_content_type(),
+            token: self.sign_at(&answer, expires_at),
+            expires_at,
+        })
+    }
+
+    /// 为答案签发令牌
+    ///
+    /// Issue a token for the answer.
+    pub fn sign(&self, answer: &str) -> String {
+        self.sign_at(answer, SystemTime::now() + self.ttl)
+    }
+
+    fn sign_at(&self, answer: &str, expires_at: SystemTime) -> String {
+        let mut token = Vec::with_capacity(TOKEN_LEN);
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        token.extend_from_slice(&nonce);
+
+        let expiry = expires_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        token.extend_from_slice(&expiry.to_be_bytes());
+
+        let token_tag = self.token_mac(&token).finalize().into_bytes();
+        let answer_tag = self.answer_mac(&token, answer).finalize().into_bytes();
+        token.extend_from_slice(&token_tag[..TAG_LEN]);
+        token.extend_from_slice(&answer_tag[..TAG_LEN]);
+
+        BASE64_URL_SAFE_NO_PAD.encode(token)
+    }
+
+    /// 验证用户输入的验证码（不区分大小写）。令牌在有效期内可以重复验证。
+    ///
+    /// Verify the user's code, ignoring ASCII case. A token can be verified repeatedly before it expires.
+    pub fn verify(&self, token: &str, code: &str) -> Result<(), TokenError> {
+        let token = self.verify_token(token)?;
+        let (payload, answer_tag) = token.split_at(NONCE_LEN + EXPIRY_LEN);
+        self.verify_answer(payload, &answer_tag[TAG_LEN..], code)
+    }
+
+    /// 验证用户输入的验证码，并确保每个令牌只能使用一次。无论验证码是否正确，令牌都会被标记为已使用。
+    ///
+    /// Verify the user's code, and make sure every token is used only once. The token is marked as used whether the
+    /// code is correct or not.
+    pub async fn verify_once(
+        &self,
+        token: &str,
+        code: &str,
+        guard: &impl ReplayGuard,
+    ) -> Result<(), TokenError> {
+        let token = self.verify_token(token)?;
+        let (payload, answer_tag) = token.split_at(NONCE_LEN + EXPIRY_LEN);
+
+        let nonce = BASE64_URL_SAFE_NO_PAD.encode(&payload[..NONCE_LEN]);
+        if !guard.mark_used(&nonce, Self::expires_at(payload)).await {
+            return Err(TokenError::Replayed);
+        }
+
+        self.verify_answer(payload, &answer_tag[TAG_LEN..], code)
+    }
+
+    /// 检查令牌的格式、签名和有效期，返回令牌的字节
+    fn verify_token(&self, token: &str) -> Result<Vec<u8>, TokenError> {
+        let token = BASE64_URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|_| TokenError::Malformed)?;
+        if token.len() != TOKEN_LEN {
+            return Err(TokenError::Malformed);
+        }
+
+        let (payload, tags) = token.split_at(NONCE_LEN + EXPIRY_LEN);
+        self.token_mac(payload)
+            .verify_truncated_left(&tags[..TAG_LEN])
+            .map_err(|_| TokenError::Malformed)?;
+
+        if Self::expires_at(payload) < SystemTime::now() {
+            return Err(TokenError::Expired);
+        }
+
+        Ok(token)
+    }
+
+    fn verify_answer(&self, payload: &[u8], tag: &[u8], code: &str) -> Result<(), TokenError> {
+        self.answer_mac(payload, code)
+            .verify_truncated_left(tag)
+            .map_err(|_| TokenError::Mismatch)
+    }
+
+    fn expires_at(payload: &[u8]) -> SystemTime {
+        let mut expiry = [0u8; EXPIRY_LEN];
+        expiry.copy_from_slice(&payload[NONCE_LEN..NONCE_LEN + EXPIRY_LEN]);
+        UNIX_EPOCH + Duration::from_secs(u64::from_be_bytes(expiry))
+    }
+
+    /// 令牌本身的签名，用于在检查答案之前识别伪造的令牌
+    fn token_mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = self.mac();
+        mac.update(b"token");
+        mac.update(payload);
+        mac
+    }
+
+    /// 答案的签名，答案不区分大小写
+    fn answer_mac(&self, payload: &[u8], answer: &str) -> HmacSha256 {
+        let mut mac = self.mac();
+        mac.update(b"answer");
+        mac.update(payload);
+        mac.update(answer.to_ascii_lowercase().as_bytes());
+        mac
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+}
+
+impl std::fmt::Debug for TokenSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenSigner")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::captcha::spec::SpecCaptcha;
+    use crate::NewCaptcha;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn it_verifies_issued_captchas() {
+        let signer = TokenSigner::new(KEY);
+        let mut captcha = SpecCaptcha::new();
+        let signed = signer.issue(&mut captcha).unwrap();
+        assert!(signed.image.starts_with(b"\x89PNG"));
+
+        let code: String = captcha.get_chars().unwrap().iter().collect();
+        assert_eq!(signer.verify(&signed.token, &code), Ok(()));
+        assert_eq!(
+            signer.verify(&signed.token, &code.to_ascii_lowercase()),
+            Ok(())
+        );
+        assert_eq!(
+            signer.verify(&signed.token, "wrong"),
+            Err(TokenError::Mismatch)
+        );
+        assert_eq!(
+            TokenSigner::new(b"another key").verify(&signed.token, &code),
+            Err(TokenError::Malformed)
+        );
+    }
+
+    #[test]
+    fn it_rejects_expired_and_tampered_tokens() {
+        let signer = TokenSigner::new(KEY);
+        let expired = signer.sign_at("abcd", SystemTime::now() - Duration::from_secs(1));
+        assert_eq!(signer.verify(&expired, "abcd"), Err(TokenError::Expired));
+
+        let mut token = BASE64_URL_SAFE_NO_PAD.decode(signer.sign("abcd")).unwrap();
+        token[NONCE_LEN] ^= 1;
+        let token = BASE64_URL_SAFE_NO_PAD.encode(token);
+        assert_eq!(signer.verify(&token, "abcd"), Err(TokenError::Malformed));
+        assert_eq!(signer.verify("garbage", "abcd"), Err(TokenError::Malformed));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_replayed_tokens() {
+        let signer = TokenSigner::new(KEY);
+        let guard = MemoryReplayGuard::new();
+        let token = signer.sign("abcd");
+
+        assert_eq!(signer.verify_once(&token, "abcd", &guard).await, Ok(()));
+        assert_eq!(
+            signer.verify_once(&token, "abcd", &guard).await,
+            Err(TokenError::Replayed)
+        );
+
+        let token = signer.sign("abcd");
+        assert_eq!(
+            signer.verify_once(&token, "wrong", &guard).await,
+            Err(TokenError::Mismatch)
+        );
+        assert_eq!(
+            signer.verify_once(&token, "abcd", &guard).await,
+            Err(TokenError::Replayed)
+        );
+    }
+}
@@ -32,27 +32,30 @@ impl From<(u8, u8, u8)> for Color {
     }
 }
 
-impl Into<(u8, u8, u8, u8)> for Color {
-    fn into(self) -> (u8, u8, u8, u8) {
+impl From<Color> for (u8, u8, u8, u8) {
+    fn from(value: Color) -> Self {
         (
-            (self.0 * 255.0) as u8,
-            (self.1 * 255.0) as u8,
-            (self.2 * 255.0) as u8,
-            (self.3 * 255.0) as u8,
+            (value.0 * 255.0) as u8,
+            (value.1 * 255.0) as u8,
+            (value.2 * 255.0) as u8,
+            (value.3 * 255.0) as u8,
         )
     }
 }
 
-impl Into<u32> for Color {
-    fn into(self) -> u32 {
-        let color: (u8, u8, u8, u8) = self.into();
-        (color.0 as u32) << 24 + (color.1 as u32) << 16 + (color.2 as u32) << 8 + (color.3 as u32)
+impl From<Color> for u32 {
+    fn from(value: Color) -> Self {
+        let color: (u8, u8, u8, u8) = value.into();
+        ((color.0 as u32) << 24)
+            + ((color.1 as u32) << 16)
+            + ((color.2 as u32) << 8)
+            + (color.3 as u32)
     }
 }
 
-impl Into<raqote::Color> for Color {
-    fn into(self) -> raqote::Color {
-        let color: (u8, u8, u8, u8) = self.into();
+impl From<Color> for raqote::Color {
+    fn from(value: Color) -> Self {
+        let color: (u8, u8, u8, u8) = value.into();
         raqote::Color::new(color.3, color.0, color.1, color.2)
     }
 }
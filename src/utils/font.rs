@@ -1,46 +1,234 @@
+use font_kit::family_name::FamilyName;
 use font_kit::font::Font;
+use font_kit::handle::Handle;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+use lazy_static::lazy_static;
 use log::error;
+use parking_lot::RwLock;
 use rust_embed::RustEmbed;
-use std::error::Error;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 
 #[derive(RustEmbed)]
 #[folder = "resources/"]
 struct FontAssets;
 
-// lazy_static! {
-//     pub(crate) static ref FONTS: RwLock<HashMap<String, Arc<Font>>> = Default::default();
-// }
+/// 字体数据及其在字体集合中的序号
+type FontData = (Arc<Vec<u8>>, u32);
 
-pub fn get_font(font_name: &str) -> Option<Arc<Font>> {
-    // let fonts_cell = FONTS.get_or_init(|| Default::default());
-    // let guard = fonts_cell.read();
-    //
-    // if guard.contains_key(font_name) {
-    //     Some(guard.get(font_name).unwrap().clone())
-    // } else {
-    //     drop(guard);
+/// 每个线程最多缓存的用户字体数量
+const MAX_DATA_FONTS: usize = 16;
 
-    if let Ok(Some(font)) = load_font(font_name) {
-        // let mut guard = fonts_cell.write();
-        let font = Arc::new(font);
-        // guard.insert(String::from(font_name), font.clone());
-        Some(font)
-    } else {
-        None
+lazy_static! {
+    /// 进程内共享的字体数据缓存，键为字体名称；找不到的字体也会被记录，避免重复查找系统字体
+    static ref FONT_DATA: RwLock<HashMap<String, Option<FontData>>> = Default::default();
+}
+
+thread_local! {
+    /// 已解析的字体。font-kit在每个线程中各自创建FreeType库（`FT_Library`），解析出的字体绑定在创建它的线程的库上，
+    /// 既不是`Send`也不能在其他线程中使用，因此无法在进程内共享，只能每个线程各自缓存一份：字体数据在进程内只读取一次，
+    /// 但每个新线程第一次使用某个字体时仍需解析一次，参见`benches/render.rs`中的`new thread`基准
+    static FONTS: RefCell<FontCache> = Default::default();
+}
+
+/// 一个线程内已解析的字体
+#[derive(Default)]
+struct FontCache {
+    fonts: HashMap<FontKey, (Rc<Font>, u64)>,
+
+    /// 每次访问递增的计数，用于找出最久未使用的用户字体
+    clock: u64,
+}
+
+impl FontCache {
+    fn get(&mut self, key: &FontKey) -> Option<Rc<Font>> {
+        self.clock += 1;
+        let (font, last_used) = self.fonts.get_mut(key)?;
+        *last_used = self.clock;
+        Some(font.clone())
     }
-    // }
+
+    /// 放入字体；用户字体可能被反复创建，超出数量限制时淘汰最久未使用的一个用户字体
+    fn insert(&mut self, key: FontKey, font: Rc<Font>) {
+        if matches!(key, FontKey::Data(_)) {
+            let data_fonts = self
+                .fonts
+                .iter()
+                .filter(|(k, _)| matches!(k, FontKey::Data(_)));
+            if data_fonts.clone().count() >= MAX_DATA_FONTS {
+                let oldest = data_fonts
+                    .min_by_key(|(_, (_, last_used))| *last_used)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    self.fonts.remove(&oldest);
+                }
+            }
+        }
+        self.clock += 1;
+        self.fonts.insert(key, (font, self.clock));
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum FontKey {
+    /// 内置字体的文件名或系统字体的字体族名
+    Named(String),
+
+    /// 用户字体的编号
+    Data(usize),
 }
 
-pub fn load_font(font_name: &str) -> Result<Option<Font>, Box<dyn Error>> {
+/// 从缓存中取出已解析的字体，未命中时解析并放入缓存
+fn get_or_parse(key: FontKey, data: impl FnOnce() -> Option<FontData>) -> Option<Rc<Font>> {
+    if let Some(font) = FONTS.with(|fonts| fonts.borrow_mut().get(&key)) {
+        return Some(font);
+    }
+
+    let (data, index) = data()?;
+    let font = Rc::new(Font::from_bytes(data, index).ok()?);
+
+    FONTS.with(|fonts| fonts.borrow_mut().insert(key, font.clone()));
+
+    Some(font)
+}
+
+/// 按名称获取字体，先查找内置字体，再查找系统字体
+pub fn get_font(font_name: &str) -> Option<Rc<Font>> {
+    get_or_parse(FontKey::Named(font_name.into()), || {
+        get_font_data(font_name)
+    })
+}
+
+/// 由字体数据加载字体
+pub fn get_font_from_data(id: usize, data: Arc<Vec<u8>>) -> Option<Rc<Font>> {
+    get_or_parse(FontKey::Data(id), || Some((data, 0)))
+}
+
+/// 从进程内缓存中获取字体数据
+fn get_font_data(font_name: &str) -> Option<FontData> {
+    if let Some(data) = FONT_DATA.read().get(font_name) {
+        return data.clone();
+    }
+
+    let data = load_font_data(font_name);
+    FONT_DATA
+        .write()
+        .entry(font_name.into())
+        .or_insert(data)
+        .clone()
+}
+
+fn load_font_data(font_name: &str) -> Option<FontData> {
     match FontAssets::get(font_name) {
-        Some(assets) => {
-            let font = Font::from_bytes(Arc::new(Vec::from(assets.data)), 0)?;
-            Ok(Some(font))
-        }
-        None => {
-            error!("Unable to find the specified font.");
-            Ok(None)
+        Some(assets) => Some((Arc::new(assets.data.into_owned()), 0)),
+        None => match load_system_font_data(font_name) {
+            Some(data) => Some(data),
+            None => {
+                error!("Unable to find the specified font.");
+                None
+            }
+        },
+    }
+}
+
+/// 按字体族名从系统中加载字体
+pub fn load_system_font(family_name: &str) -> Option<Font> {
+    let (data, index) = load_system_font_data(family_name)?;
+    Font::from_bytes(data, index).ok()
+}
+
+/// 按字体族名从系统中读取字体数据
+fn load_system_font_data(family_name: &str) -> Option<FontData> {
+    let handle = SystemSource::new()
+        .select_best_match(&[FamilyName::Title(family_name.into())], &Properties::new())
+        .ok()?;
+
+    // 字体族匹配不到时会退回到系统默认字体，这里需要确认族名一致
+    let font = handle.load().ok()?;
+    if !font.family_name().eq_ignore_ascii_case(family_name) {
+        return None;
+    }
+
+    match handle {
+        Handle::Path { path, font_index } => {
+            Some((Arc::new(std::fs::read(path).ok()?), font_index))
         }
+        Handle::Memory { bytes, font_index } => Some((bytes, font_index)),
+    }
+}
+
+/// 常见的中文字体族名，按优先级排列
+const CJK_FAMILY_NAMES: [&str; 14] = [
+    "Noto Sans CJK SC",
+    "Noto Serif CJK SC",
+    "Source Han Sans SC",
+    "Source Han Serif SC",
+    "WenQuanYi Micro Hei",
+    "WenQuanYi Zen Hei",
+    "AR PL UKai CN",
+    "AR PL UMing CN",
+    "KaiTi",
+    "SimHei",
+    "Microsoft YaHei",
+    "PingFang SC",
+    "Hiragino Sans GB",
+    "STHeiti",
+];
+
+lazy_static! {
+    static ref CJK_FONT_NAME: Option<String> = CJK_FAMILY_NAMES
+        .iter()
+        .find(|name| {
+            load_system_font(name)
+                .map(|font| font.glyph_for_char('中').is_some())
+                .unwrap_or(false)
+        })
+        .map(|name| String::from(*name));
+}
+
+/// 获取系统中可用的中文字体名称
+pub fn cjk_font_name() -> Option<&'static str> {
+    CJK_FONT_NAME.as_deref()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_each_font_once_per_thread() {
+        let font = get_font("lexo.ttf").unwrap();
+        assert!(Rc::ptr_eq(&font, &get_font("lexo.ttf").unwrap()));
+        assert!(FONT_DATA.read().contains_key("lexo.ttf"));
+
+        let data = Arc::new(FontAssets::get("lexo.ttf").unwrap().data.into_owned());
+        let font = get_font_from_data(usize::MAX, data.clone()).unwrap();
+        assert!(Rc::ptr_eq(
+            &font,
+            &get_font_from_data(usize::MAX, data).unwrap()
+        ));
+
+        assert!(get_font("no-such-font").is_none());
+        assert!(matches!(FONT_DATA.read().get("no-such-font"), Some(None)));
+    }
+    #[test]
+    fn it_evicts_the_least_recently_used_data_font() {
+        let data = Arc::new(FontAssets::get("lexo.ttf").unwrap().data.into_owned());
+        let named = get_font("lexo.ttf").unwrap();
+        let fonts: Vec<_> = (0..MAX_DATA_FONTS)
+            .map(|id| get_font_from_data(id, data.clone()).unwrap())
+            .collect();
+
+        // 使用第0个字体后再放入新字体，被淘汰的是第1个
+        get_font_from_data(0, data.clone());
+        get_font_from_data(MAX_DATA_FONTS, data.clone());
+        let cached = |id| FONTS.with(|cache| cache.borrow_mut().get(&FontKey::Data(id)));
+        assert!(Rc::ptr_eq(&cached(0).unwrap(), &fonts[0]));
+        assert!(cached(1).is_none());
+        assert!(Rc::ptr_eq(&cached(2).unwrap(), &fonts[2]));
+        assert!(Rc::ptr_eq(&named, &get_font("lexo.ttf").unwrap()));
     }
 }
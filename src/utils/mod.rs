@@ -3,3 +3,7 @@
 pub(crate) mod color;
 pub(crate) mod font;
 pub(crate) mod png;
+#[cfg(test)]
+pub(crate) mod test_font;
+#[cfg(test)]
+pub(crate) mod test_image;
@@ -14,27 +14,29 @@ impl WritePng for DrawTarget {
         encoder.set_color(png::ColorType::Rgba);
         encoder.set_depth(png::BitDepth::Eight);
         let mut writer = encoder.write_header()?;
-        let buf = self.get_data();
-        let mut output = Vec::with_capacity(buf.len() * 4);
-
-        for pixel in buf {
-            let a = (pixel >> 24) & 0xffu32;
-            let mut r = (pixel >> 16) & 0xffu32;
-            let mut g = (pixel >> 8) & 0xffu32;
-            let mut b = (pixel >> 0) & 0xffu32;
-
-            if a > 0u32 {
-                r = r * 255u32 / a;
-                g = g * 255u32 / a;
-                b = b * 255u32 / a;
-            }
-
-            output.push(r as u8);
-            output.push(g as u8);
-            output.push(b as u8);
-            output.push(a as u8);
-        }
-
-        writer.write_image_data(&output)
+        writer.write_image_data(&rgba_data(self))
     }
 }
+
+/// 将预乘透明度的ARGB像素转换为PNG和GIF编码器所需的RGBA字节
+pub(crate) fn rgba_data(dt: &DrawTarget) -> Vec<u8> {
+    let buf = dt.get_data();
+    let mut output = Vec::with_capacity(buf.len() * 4);
+
+    for pixel in buf {
+        let a = (pixel >> 24) & 0xffu32;
+        let r = (pixel >> 16) & 0xffu32;
+        let g = (pixel >> 8) & 0xffu32;
+        let b = pixel & 0xffu32;
+
+        // 去除预乘的透明度；完全透明的像素保持原样
+        let unpremultiply = |c: u32| (c * 255u32).checked_div(a).unwrap_or(c);
+
+        output.push(unpremultiply(r) as u8);
+        output.push(unpremultiply(g) as u8);
+        output.push(unpremultiply(b) as u8);
+        output.push(a as u8);
+    }
+
+    output
+}
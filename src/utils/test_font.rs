@@ -0,0 +1,171 @@
+//! 测试用的字体
+//!
+//! 仓库中没有可以随包发布的中文字体，这里在测试时生成最小的TrueType字体：指定的每个字符都映射到同一个方框字形，
+//! 足以走通中文验证码或自定义字符集的排版和绘制流程。
+
+use crate::base::randoms::HAN;
+
+/// 字形的外框和内框（字体单位），外框顺时针、内框逆时针，画出一个空心方框
+const CONTOURS: [[(i16, i16); 4]; 2] = [
+    [(50, -80), (50, 820), (950, 820), (950, -80)],
+    [(200, 70), (800, 70), (800, 670), (200, 670)],
+];
+
+/// 生成包含`HAN`中全部汉字的字体数据
+pub(crate) fn han_font() -> Vec<u8> {
+    font_for(HAN)
+}
+
+/// 生成只包含给定字符的字体数据，字符须位于基本多文种平面内
+pub(crate) fn font_for(chars: &str) -> Vec<u8> {
+    let mut chars: Vec<u16> = chars.chars().map(|c| c as u16).collect();
+    chars.sort_unstable();
+    chars.dedup();
+
+    let tables: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"OS/2", os2()),
+        (b"cmap", cmap(&chars)),
+        (b"glyf", glyf()),
+        (b"head", head()),
+        (b"hhea", hhea()),
+        (b"hmtx", hmtx()),
+        (b"loca", loca()),
+        (b"maxp", maxp()),
+        (b"post", post()),
+    ];
+
+    let mut font = Vec::new();
+    font.extend(0x0001_0000u32.to_be_bytes());
+    font.extend((tables.len() as u16).to_be_bytes());
+    font.extend(128u16.to_be_bytes()); // searchRange
+    font.extend(3u16.to_be_bytes()); // entrySelector
+    font.extend((tables.len() as u16 * 16 - 128).to_be_bytes()); // rangeShift
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut data = Vec::new();
+    for (tag, table) in &tables {
+        font.extend(*tag);
+        font.extend(checksum(table).to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((table.len() as u32).to_be_bytes());
+
+        data.extend(table);
+        data.resize((data.len() + 3) & !3, 0);
+        offset = 12 + 16 * tables.len() + data.len();
+    }
+    font.extend(data);
+    font
+}
+
+fn checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn be16(values: &[i32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| (*v as u16).to_be_bytes())
+        .collect()
+}
+
+fn head() -> Vec<u8> {
+    let mut table = Vec::new();
+    table.extend(0x0001_0000u32.to_be_bytes()); // version
+    table.extend(0x0001_0000u32.to_be_bytes()); // fontRevision
+    table.extend(0u32.to_be_bytes()); // checkSumAdjustment
+    table.extend(0x5F0F_3CF5u32.to_be_bytes()); // magicNumber
+    table.extend(be16(&[0x000B, 1000])); // flags, unitsPerEm
+    table.extend([0; 16]); // created, modified
+    table.extend(be16(&[50, -80, 950, 820])); // xMin, yMin, xMax, yMax
+    table.extend(be16(&[0, 8, 2, 0, 0])); // macStyle ... glyphDataFormat
+    table
+}
+
+fn hhea() -> Vec<u8> {
+    let mut table = 0x0001_0000u32.to_be_bytes().to_vec();
+    table.extend(be16(&[880, -120, 0, 1000, 50, 50, 950, 1, 0, 0]));
+    table.extend(be16(&[0, 0, 0, 0, 0, 2])); // reserved, metricDataFormat, numberOfHMetrics
+    table
+}
+
+fn maxp() -> Vec<u8> {
+    let mut table = 0x0001_0000u32.to_be_bytes().to_vec();
+    table.extend(be16(&[2, 8, 2, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]));
+    table
+}
+
+fn hmtx() -> Vec<u8> {
+    be16(&[1000, 0, 1000, 50])
+}
+
+/// 第0个字形为空的`.notdef`，第1个字形为方框
+fn glyf() -> Vec<u8> {
+    let mut table = be16(&[CONTOURS.len() as i32, 50, -80, 950, 820, 3, 7, 0]);
+    table.extend([0x01; 8]); // 全部为曲线上的点
+    for axis in 0..2 {
+        let mut last = 0;
+        for &point in CONTOURS.iter().flatten() {
+            let v = if axis == 0 { point.0 } else { point.1 };
+            table.extend((v - last).to_be_bytes());
+            last = v;
+        }
+    }
+    table
+}
+
+fn loca() -> Vec<u8> {
+    let len = glyf().len() as i32;
+    be16(&[0, 0, len / 2])
+}
+
+fn cmap(chars: &[u16]) -> Vec<u8> {
+    // 每个汉字单独一段，最后是必需的0xFFFF段
+    let ends: Vec<i32> = chars.iter().map(|c| *c as i32).chain([0xFFFF]).collect();
+    let seg_count = ends.len() as i32;
+    let search_range: i32 = 2 << (31 - (seg_count as u32).leading_zeros());
+    let entry_selector = (search_range / 2).trailing_zeros() as i32;
+
+    let mut subtable = be16(&[4, 16 + seg_count * 8, 0, seg_count * 2]);
+    subtable.extend(be16(&[
+        search_range,
+        entry_selector,
+        seg_count * 2 - search_range,
+    ]));
+    subtable.extend(be16(&ends));
+    subtable.extend(be16(&[0]));
+    subtable.extend(be16(&ends));
+    let deltas: Vec<i32> = chars
+        .iter()
+        .map(|c| (1 - *c as i32) & 0xFFFF)
+        .chain([1])
+        .collect();
+    subtable.extend(be16(&deltas));
+    subtable.extend(be16(&vec![0; ends.len()]));
+
+    let mut table = be16(&[0, 1, 3, 1]);
+    table.extend(12u32.to_be_bytes());
+    table.extend(subtable);
+    table
+}
+
+fn post() -> Vec<u8> {
+    let mut table = 0x0003_0000u32.to_be_bytes().to_vec();
+    table.extend([0; 28]);
+    table
+}
+
+fn os2() -> Vec<u8> {
+    let mut table = be16(&[4, 1000, 400, 5, 0]);
+    table.extend(be16(&[650, 700, 0, 140, 650, 700, 0, 480, 50, 250, 0]));
+    table.extend([0; 10]); // panose
+    table.extend([0; 16]); // ulUnicodeRange
+    table.extend(*b"NONE");
+    table.extend(be16(&[0x40, 0x4E00, 0x9FFF, 880, -120, 0, 880, 120]));
+    table.extend([0; 8]); // ulCodePageRange
+    table.extend(be16(&[500, 700, 0, 32, 0]));
+    table
+}
@@ -0,0 +1,96 @@
+//! 测试用的图像解码
+//!
+//! 将渲染出的PNG或GIF解码为RGBA像素，以便检查字符、背景和干扰元素实际画在了哪里，而不只是比较字节是否变化。
+
+/// 解码后的一帧图像
+pub(crate) struct Pixels {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    rgba: Vec<[u8; 4]>,
+}
+
+impl Pixels {
+    fn new(width: usize, height: usize, data: &[u8]) -> Self {
+        let rgba = data
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
+            .collect();
+        Self {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// `(x, y)`处的像素
+    pub(crate) fn at(&self, x: usize, y: usize) -> [u8; 4] {
+        self.rgba[y * self.width + x]
+    }
+
+    /// 所有像素
+    pub(crate) fn all(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+        self.rgba.iter().copied()
+    }
+
+    /// 最外圈的像素
+    pub(crate) fn border(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+        let (w, h) = (self.width, self.height);
+        (0..self.rgba.len())
+            .filter(move |i| i % w == 0 || i % w == w - 1 || i / w == 0 || i / w == h - 1)
+            .map(move |i| self.rgba[i])
+    }
+
+    /// 是否有某个颜色通道与背景相差超过`threshold`
+    pub(crate) fn is_ink(&self, x: usize, y: usize, background: [u8; 3], threshold: u8) -> bool {
+        let pixel = self.at(x, y);
+        (0..3).any(|c| pixel[c].abs_diff(background[c]) > threshold)
+    }
+
+    /// 与背景明显不同的像素的坐标
+    pub(crate) fn ink(&self, background: [u8; 3], threshold: u8) -> Vec<(usize, usize)> {
+        let mut ink = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.is_ink(x, y, background, threshold) {
+                    ink.push((x, y));
+                }
+            }
+        }
+        ink
+    }
+
+    /// `(x, y)`周围`distance`像素（切比雪夫距离）内是否有与背景明显不同的像素
+    pub(crate) fn has_ink_near(
+        &self,
+        (x, y): (usize, usize),
+        distance: usize,
+        background: [u8; 3],
+        threshold: u8,
+    ) -> bool {
+        let xs = x.saturating_sub(distance)..=(x + distance).min(self.width - 1);
+        let ys = y.saturating_sub(distance)..=(y + distance).min(self.height - 1);
+        ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+            .any(|(x, y)| self.is_ink(x, y, background, threshold))
+    }
+}
+
+/// 解码PNG图像
+pub(crate) fn decode_png(data: &[u8]) -> Pixels {
+    let mut reader = png::Decoder::new(data).read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    Pixels::new(info.width as usize, info.height as usize, &buf)
+}
+
+/// 解码GIF图像的每一帧
+pub(crate) fn decode_gif(data: &[u8]) -> Vec<Pixels> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(data).unwrap();
+    let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push(Pixels::new(width, height, &frame.buffer));
+    }
+    frames
+}